@@ -22,13 +22,35 @@ function sleep(t) {
   return new Promise((resolve) => setTimeout(resolve, t * 1000));
 }
 
+// the server sanitizes the HTML, keeping formatting like italics, rules and
+// tables. Footnote buttons become links to the footnotes sent alongside.
 function getContent() {
-  const contents = Array.from(document.querySelectorAll(".chapter-content p"));
-  contents.map((el) => el.querySelector('button[type="button"]')?.remove());
+  const container = document.querySelector(".chapter-content").cloneNode(true);
+  const footnotes = [];
+  container.querySelectorAll('button[type="button"]').forEach((el) => {
+    const note = el.getAttribute("aria-label") || el.title;
+    if (!note) {
+      el.remove();
+      return;
+    }
+
+    const content = document.createElement("p");
+    content.textContent = note;
+    const marker = el.innerText.trim() || `${footnotes.length + 1}`;
+    footnotes.push({ marker, content: content.outerHTML });
 
-  const content = contents.map((el) => el.innerText).join("<p>");
+    const link = document.createElement("a");
+    link.href = `#footnote-${footnotes.length}`;
+    link.textContent = marker;
+    el.replaceWith(link);
+  });
+
+  // images are fetched by the server, relative URLs would be meaningless
+  container.querySelectorAll("img").forEach((el) => {
+    el.setAttribute("src", el.src);
+  });
 
-  return content;
+  return { content: container.innerHTML, footnotes };
 }
 
 function green() {
@@ -57,7 +79,7 @@ async function onClick() {
     return;
   }
 
-  const content = getContent();
+  const { content, footnotes } = getContent();
 
   const chapter = {
     book: metadata.novelName,
@@ -65,7 +87,11 @@ async function onClick() {
     number_in_book: metadata.chapterNo,
     author: metadata.novelWriter,
     translator: metadata.novelTranslator,
+    language: document.documentElement.lang || undefined,
     content,
+    html: true,
+    footnotes,
+    url: location.href,
   };
 
   send(chapter)
@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A generated ePub kept around to be downloaded or sent again, without its
+/// content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StoredArtifact {
+    pub id: i32,
+    pub export_id: i32,
+    // exports too big for a single ePub are split in volumes, from 1
+    pub volume: i32,
+    pub filename: String,
+    // in bytes
+    pub size: i64,
+    // sha256 of the content, hex encoded
+    pub checksum: String,
+    pub created_at: DateTime<Utc>,
+}
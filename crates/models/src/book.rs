@@ -1,5 +1,8 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -10,4 +13,32 @@ pub struct Book {
     pub author: Option<String>,
     pub translator: Option<String>,
     pub cover: Option<String>,
+    // appended to the stylesheet of this book's exports, after the user's
+    pub custom_css: Option<String>,
+    // `dc:identifier` of its exports, so re-exports replace the previous ones
+    pub identifier: Uuid,
+    // BCP 47 tag, English when unknown
+    pub language: Option<String>,
+    pub description: Option<String>,
+    pub subjects: Vec<String>,
+    pub epub_templates: EpubTemplates,
+}
+
+/// Markup replacing the default templates of this book's ePubs, `{{ name }}`
+/// standing for the page variables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EpubTemplates {
+    pub title_page: Option<String>,
+    pub chapter_header: Option<String>,
+    pub chapter_footer: Option<String>,
+    pub colophon: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl From<JsonValue> for EpubTemplates {
+    fn from(value: JsonValue) -> Self {
+        serde_json::from_value(value).unwrap()
+    }
 }
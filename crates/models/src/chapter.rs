@@ -3,6 +3,9 @@ use std::fmt::Display;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "serde")]
+use serde_json::Value as JsonValue;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Chapter {
@@ -11,6 +14,28 @@ pub struct Chapter {
     pub name: String,
     pub content: String,
     pub number_in_book: i32,
+    // sanitized HTML, otherwise text paragraphs joined with `<p>`
+    pub html: bool,
+    pub footnotes: Footnotes,
+    // page it was read on
+    pub url: Option<String>,
+}
+
+/// Translator notes of a chapter.
+///
+/// The chapter content references them with links to `#footnote-<n>`, `n`
+/// starting at 1.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Footnotes(pub Vec<Footnote>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Footnote {
+    // shown where the note is referenced, usually its number
+    pub marker: String,
+    // same format as the chapter content
+    pub content: String,
 }
 
 impl Display for Chapter {
@@ -22,3 +47,10 @@ impl Display for Chapter {
         )
     }
 }
+
+#[cfg(feature = "serde")]
+impl From<JsonValue> for Footnotes {
+    fn from(value: JsonValue) -> Self {
+        serde_json::from_value(value).unwrap()
+    }
+}
@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Outcome of sending an export to one destination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeliveryStatus {
+    pub id: i32,
+    pub export_id: i32,
+    // name of the delivery backend, such as `discord` or `smtp`
+    pub destination: String,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub attempts: i32,
+    // unset once delivered, or when the destination gave up
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    // each volume of an export is sent on its own
+    pub volume: i32,
+}
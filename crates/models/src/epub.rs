@@ -1,8 +1,97 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::{book::EpubTemplates, chapter::Footnote};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Epub {
     pub title: String,
     pub author: Option<String>,
     pub translator: Option<String>,
-    pub chapters: Vec<(String, String)>,
     pub cover: Option<String>,
+    // for e-ink readers, smaller and looking the same
+    pub grayscale_cover: bool,
+    pub style: StyleProfile,
+    pub toc_position: TocPosition,
+    // chapter numbers before their title in the tables of contents
+    pub numbered_toc: bool,
+    // user and book stylesheets, appended after the profile's
+    pub custom_css: Option<String>,
+    pub identifier: Uuid,
+    pub language: String,
+    pub description: Option<String>,
+    pub subjects: Vec<String>,
+    // only set for exports holding part of a book
+    pub series: Option<Series>,
+    // first and last chapter numbers, also for exports holding part of a book
+    pub chapter_range: Option<(i32, i32)>,
+    pub exported_on: NaiveDate,
+    // the book's, anthologies use the defaults
+    pub templates: EpubTemplates,
+}
+
+/// A page of the ePub, handed to the builder one at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpubChapter {
+    pub title: String,
+    // in the book, or in the export for anthologies
+    pub number: i32,
+    // where the chapter was read
+    pub url: Option<String>,
+    // HTML
+    pub content: String,
+    // content turned into HTML too
+    pub footnotes: Vec<Footnote>,
+}
+
+/// Chapter listed in the table of contents, known before the chapters are
+/// streamed so it can come first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    pub number: i32,
+    pub title: String,
+}
+
+/// Image of a chapter, stored in the ePub instead of fetched by the reader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpubImage {
+    // path in the ePub, named after the hash of the image
+    pub filename: String,
+    pub mime: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// Calibre series of a partial export, so its parts sort in reading order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Series {
+    pub name: String,
+    // first chapter of the export
+    pub index: i32,
+}
+
+/// Base stylesheet of a generated ePub, depending on the reader it's meant for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StyleProfile {
+    #[default]
+    Kindle,
+    Kobo,
+    EInk,
+    LargePrint,
+}
+
+/// Where the table of contents page goes, the navigation menus of readers
+/// list the chapters either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TocPosition {
+    // right after the title page
+    #[default]
+    Front,
+    // after the last chapter
+    Back,
 }
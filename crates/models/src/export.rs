@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::epub::{StyleProfile, TocPosition};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -81,20 +83,85 @@ pub struct Export {
     pub processed_at: Option<DateTime<Utc>>,
     pub sent: bool,
     pub error: Option<String>,
+    // refreshed by the worker while it holds the export
+    #[cfg_attr(feature = "serde", serde(with = "opt_date_fmt"))]
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    pub attempts: i32,
+    // set while waiting for a retry
+    #[cfg_attr(feature = "serde", serde(with = "opt_date_fmt"))]
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub options: ExportOptions,
+    // who asked for it, unknown for older exports
+    pub user_id: Option<i32>,
+    pub exported_chapters: ExportedChapters,
+    // set once every volume is stored
+    pub volumes: Option<i32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ExportKinds {
-    Anthology,
+    Anthology {
+        title: String,
+        parts: Vec<AnthologyPart>,
+    },
+    // book id
     FullBook(i32),
+    // chapter id
     SingleChapter(i32),
-    // will error if there is a blank spot in the range
-    ChaptersRange { book_id: i32, chapters: (i32, i32) },
+    // will error if there is a blank spot in the range, unless gaps are allowed
+    ChaptersRange {
+        book_id: i32,
+        chapters: (i32, i32),
+    },
+    // book id, the chapters are picked when the export is processed
+    SinceLastExport(i32),
 }
 
+/// A book, or a range of its chapters, bundled in an anthology.
+///
+/// Parts are exported in the order they are listed.
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum ExportState {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AnthologyPart {
+    pub book_id: i32,
+    // the whole book when missing
+    pub chapters: Option<(i32, i32)>,
+}
+
+/// Highest chapter number of each book id in a generated export.
+///
+/// Once the export is delivered, they become the watermarks
+/// [`ExportKinds::SinceLastExport`] starts from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExportedChapters(pub BTreeMap<i32, i32>);
+
+/// How an export is generated, whatever its kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExportOptions {
+    // missing chapters get a placeholder page instead of failing the export
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow_gaps: bool,
+    // split in volumes of at most this many chapters, on top of the size limit
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_chapters: Option<i32>,
+    // base stylesheet, custom CSS is added on top of it
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub style: StyleProfile,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub grayscale_cover: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub toc_position: TocPosition,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub numbered_toc: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ExportState {
     Created,
     Processing,
     Processed,
@@ -103,7 +170,7 @@ enum ExportState {
 }
 
 impl Export {
-    fn get_state(&self) -> ExportState {
+    pub fn get_state(&self) -> ExportState {
         match self.error {
             Some(_) => ExportState::Failed,
             None => match self.sent {
@@ -134,7 +201,12 @@ impl Display for ExportKinds {
                 "{}: Chapters from {} to {}",
                 book_id, chapters.0, chapters.1
             ),
-            _ => todo!(),
+            ExportKinds::FullBook(book_id) => write!(f, "{}: Full book", book_id),
+            ExportKinds::SingleChapter(chapter_id) => write!(f, "Chapter {}", chapter_id),
+            ExportKinds::SinceLastExport(book_id) => write!(f, "{}: Since last export", book_id),
+            ExportKinds::Anthology { title, parts } => {
+                write!(f, "{}: Anthology of {} parts", title, parts.len())
+            }
         }
     }
 }
@@ -159,3 +231,17 @@ impl From<JsonValue> for ExportKinds {
         serde_json::from_value(value).unwrap()
     }
 }
+
+#[cfg(feature = "serde")]
+impl From<JsonValue> for ExportOptions {
+    fn from(value: JsonValue) -> Self {
+        serde_json::from_value(value).unwrap()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<JsonValue> for ExportedChapters {
+    fn from(value: JsonValue) -> Self {
+        serde_json::from_value(value).unwrap()
+    }
+}
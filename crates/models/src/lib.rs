@@ -1,7 +1,10 @@
+pub mod artifact;
 pub mod book;
 pub mod chapter;
+pub mod delivery;
 pub mod epub;
 pub mod export;
+pub mod subscription;
 pub mod user;
 
 #[cfg(feature = "sqlx")]
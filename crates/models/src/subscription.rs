@@ -0,0 +1,37 @@
+use chrono::{DateTime, NaiveTime, Utc};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Rules queuing "since last export" exports of a book for a user, on a
+/// schedule or once enough chapters piled up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Subscription {
+    pub id: i32,
+    pub user_id: i32,
+    pub book_id: i32,
+    // cron expression, in UTC
+    pub schedule: Option<String>,
+    // number of chapters after the user's watermark
+    pub threshold: Option<i32>,
+    // no export is queued between these times, in UTC
+    pub quiet_start: Option<NaiveTime>,
+    pub quiet_end: Option<NaiveTime>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    // highest chapter of the book when the last export was queued
+    pub last_run_chapter: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Subscription {
+    /// Whether `time` falls in the quiet hours, which may span midnight.
+    pub fn is_quiet(&self, time: NaiveTime) -> bool {
+        match (self.quiet_start, self.quiet_end) {
+            (Some(start), Some(end)) if start <= end => start <= time && time < end,
+            (Some(start), Some(end)) => start <= time || time < end,
+            _ => false,
+        }
+    }
+}
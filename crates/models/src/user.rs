@@ -11,6 +11,8 @@ pub struct User {
     pub username: String,
     pub avatar: Option<String>,
     pub token: Option<String>,
+    // appended to the stylesheet of every export
+    pub custom_css: Option<String>,
 }
 
 impl User {
@@ -21,6 +23,7 @@ impl User {
             username: "".to_string(),
             avatar: None,
             token: None,
+            custom_css: None,
         }
     }
 
@@ -31,6 +34,7 @@ impl User {
             username,
             avatar,
             token: None,
+            custom_css: None,
         }
     }
 
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE exports ADD COLUMN heartbeat_at timestamptz DEFAULT null;
+ALTER TABLE exports ADD COLUMN attempts int DEFAULT 0 NOT null;
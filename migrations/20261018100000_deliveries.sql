@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS deliveries (
+  id serial PRIMARY KEY,
+  export_id int NOT null REFERENCES exports(id) ON DELETE CASCADE,
+  destination varchar(50) NOT null,
+  created_at timestamptz DEFAULT CURRENT_TIMESTAMP NOT null,
+  sent_at timestamptz DEFAULT null,
+  error text DEFAULT null,
+  UNIQUE (export_id, destination)
+);
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE exports ADD COLUMN next_attempt_at timestamptz DEFAULT null;
+ALTER TABLE deliveries ADD COLUMN attempts int DEFAULT 0 NOT null;
+ALTER TABLE deliveries ADD COLUMN next_attempt_at timestamptz DEFAULT null;
@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS artifacts (
+  id serial PRIMARY KEY,
+  export_id int NOT null UNIQUE REFERENCES exports(id) ON DELETE CASCADE,
+  filename text NOT null,
+  title text NOT null,
+  description text NOT null,
+  size bigint NOT null,
+  -- sha256 of the content, hex encoded
+  checksum varchar(64) NOT null,
+  content bytea NOT null,
+  created_at timestamptz DEFAULT CURRENT_TIMESTAMP NOT null
+);
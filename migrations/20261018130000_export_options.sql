@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE exports ADD COLUMN options jsonb DEFAULT '{}' NOT null;
@@ -0,0 +1,13 @@
+-- Add migration script here
+ALTER TABLE exports ADD COLUMN user_id int DEFAULT null REFERENCES users(id) ON DELETE SET NULL;
+-- highest chapter number in the ePub, per book id, set once it is generated
+ALTER TABLE exports ADD COLUMN exported_chapters jsonb DEFAULT '{}' NOT null;
+
+-- last chapter of a book each user received, moved forward by delivered exports
+CREATE TABLE IF NOT EXISTS watermarks (
+  user_id int NOT null REFERENCES users(id) ON DELETE CASCADE,
+  book_id int NOT null REFERENCES books(id) ON DELETE CASCADE,
+  last_chapter int NOT null,
+  updated_at timestamptz DEFAULT CURRENT_TIMESTAMP NOT null,
+  PRIMARY KEY (user_id, book_id)
+);
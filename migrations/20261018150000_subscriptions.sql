@@ -0,0 +1,20 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS subscriptions (
+  id serial PRIMARY KEY,
+  user_id int NOT null REFERENCES users(id) ON DELETE CASCADE,
+  book_id int NOT null REFERENCES books(id) ON DELETE CASCADE,
+  -- cron expression, in UTC
+  schedule varchar(255) DEFAULT null,
+  -- number of chapters after the user's watermark
+  threshold int DEFAULT null,
+  -- no export is queued between these times, in UTC
+  quiet_start time DEFAULT null,
+  quiet_end time DEFAULT null,
+  -- next time the schedule fires
+  next_run_at timestamptz DEFAULT null,
+  last_run_at timestamptz DEFAULT null,
+  -- highest chapter of the book when the last export was queued
+  last_run_chapter int DEFAULT null,
+  created_at timestamptz DEFAULT CURRENT_TIMESTAMP NOT null,
+  CHECK (schedule IS NOT null OR threshold IS NOT null)
+);
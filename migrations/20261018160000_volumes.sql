@@ -0,0 +1,8 @@
+-- Add migration script here
+ALTER TABLE artifacts ADD COLUMN volume int DEFAULT 1 NOT null;
+ALTER TABLE artifacts DROP CONSTRAINT artifacts_export_id_key;
+ALTER TABLE artifacts ADD UNIQUE (export_id, volume);
+
+ALTER TABLE deliveries ADD COLUMN volume int DEFAULT 1 NOT null;
+ALTER TABLE deliveries DROP CONSTRAINT deliveries_export_id_destination_key;
+ALTER TABLE deliveries ADD UNIQUE (export_id, destination, volume);
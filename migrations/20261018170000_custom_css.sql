@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN custom_css text;
+ALTER TABLE books ADD COLUMN custom_css text;
@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE books ADD COLUMN identifier uuid DEFAULT gen_random_uuid() NOT null;
+ALTER TABLE books ADD COLUMN language varchar(35);
+ALTER TABLE books ADD COLUMN description text;
+ALTER TABLE books ADD COLUMN subjects text[] DEFAULT '{}' NOT null;
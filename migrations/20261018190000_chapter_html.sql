@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE chapters ADD COLUMN html boolean DEFAULT false NOT null;
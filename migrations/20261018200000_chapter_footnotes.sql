@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE chapters ADD COLUMN footnotes jsonb DEFAULT '[]' NOT null;
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE books ADD COLUMN epub_templates jsonb DEFAULT '{}' NOT null;
+ALTER TABLE chapters ADD COLUMN url text;
@@ -0,0 +1,8 @@
+-- Add migration script here
+-- set once every volume of the export is stored
+ALTER TABLE exports ADD COLUMN volumes int;
+
+-- volumes used to be stored all at once
+UPDATE exports e
+SET volumes = (SELECT count(*) FROM artifacts a WHERE a.export_id = e.id)
+WHERE EXISTS (SELECT 1 FROM artifacts a WHERE a.export_id = e.id);
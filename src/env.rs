@@ -1,5 +1,6 @@
 use std::env::var;
 
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub port: u16,
@@ -7,7 +8,7 @@ pub struct Environment {
     pub jwt_secret: String,
     pub domain: String,
     pub salt: String,
-    pub discord_webhook: String,
+    pub discord_webhook: Option<String>,
     pub discord_client_id: String,
     pub discord_client_secret: String,
     pub discord_redirect_uri: String,
@@ -21,7 +22,7 @@ impl Environment {
         let jwt_secret = var("JWT_SECRET").expect("JWT_SECRET must be set");
         let domain = var("DOMAIN").expect("DOMAIN must be set");
         let salt = var("SALT").expect("SALT must be set");
-        let discord_webhook = var("DISCORD_WEBHOOK").expect("DISCORD_WEBHOOK must be set");
+        let discord_webhook = var("DISCORD_WEBHOOK").ok();
         let discord_client_id = var("DISCORD_CLIENT_ID").expect("DISCORD_CLIENT_ID must be set");
         let discord_client_secret =
             var("DISCORD_CLIENT_SECRET").expect("DISCORD_CLIENT_SECRET must be set");
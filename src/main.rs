@@ -1,17 +1,31 @@
 mod env;
 mod pool;
 mod server;
+mod signal;
+mod worker;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use env::Environment;
 
 #[derive(Debug, Parser)]
 #[command(name = "wuxia2kindle")]
-struct Args {}
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// HTTP server receiving chapters and serving the web app
+    Ingest,
+    /// Long running process polling for exports to build and send
+    Worker(Box<worker::WorkerArgs>),
+}
 
 fn main() {
-    let _args = Args::parse();
+    let args = Args::parse();
     let env = Environment::new();
 
     if let Ok(sentry_dsn) = std::env::var("SENTRY_DSN") {
@@ -21,5 +35,16 @@ fn main() {
         }));
     }
 
-    server::start(env);
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "wuxia2kindle=debug,tower_http=debug".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    match args.command {
+        Command::Ingest => server::start(env),
+        Command::Worker(args) => worker::start(env, *args),
+    }
 }
@@ -8,6 +8,7 @@ use self::super::super::Error;
 
 use super::discord::DiscordAuth;
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct OAuthToken {
     pub access_token: String,
@@ -17,6 +18,7 @@ pub struct OAuthToken {
     pub scope: String,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct OAuthUser {
     pub id: String,
@@ -0,0 +1,87 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use models::export::{ExportKinds, ExportOptions};
+use sqlx::PgPool;
+
+use crate::{
+    server::{
+        auth::AuthKind,
+        exports::{
+            get::content_disposition,
+            validate::{rejected, validate, Invalid},
+        },
+        Error,
+    },
+    worker::{export::generate_now, images::HttpFetcher},
+};
+
+use super::EpubQuery;
+
+// bigger books take too long to generate within a request, and are better
+// split in volumes by the worker
+const MAX_SIZE: usize = 5 * 1024 * 1024;
+
+/// Generates the ePub of a book, or of a range of its chapters, and sends it
+/// back right away instead of queuing an export.
+pub async fn download_epub(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+    Query(query): Query<EpubQuery>,
+) -> Result<Response, Error> {
+    let user_id = auth.user().id;
+    let export = match (query.from, query.to) {
+        (Some(from), Some(to)) => ExportKinds::ChaptersRange {
+            book_id: id,
+            chapters: (from, to),
+        },
+        (None, None) => ExportKinds::FullBook(id),
+        _ => {
+            let message = "A range needs both a start and an end".to_owned();
+            return Ok((StatusCode::BAD_REQUEST, Html(message)).into_response());
+        }
+    };
+    let options = ExportOptions {
+        allow_gaps: query.allow_gaps,
+        max_chapters: None,
+        style: query.style,
+        grayscale_cover: query.grayscale_cover,
+        toc_position: query.toc_position,
+        numbered_toc: query.numbered_toc,
+    };
+
+    let invalid = validate(&pool, &export, &options).await?;
+    if !invalid.is_empty() {
+        return Ok(rejected(&invalid).into_response());
+    }
+
+    let generated = generate_now(
+        &pool,
+        &HttpFetcher::new(),
+        &export,
+        &options,
+        Some(user_id),
+        MAX_SIZE,
+    )
+    .await?;
+    let Some((title, bytes)) = generated else {
+        let invalid = Invalid::TooBig { max_size: MAX_SIZE };
+        return Ok(rejected(&[invalid]).into_response());
+    };
+    let filename = match (query.from, query.to) {
+        (Some(from), Some(to)) => format!("{title} (ch. {from}–{to}).epub"),
+        _ => format!("{title}.epub"),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/epub+zip".to_owned()),
+            (header::CONTENT_DISPOSITION, content_disposition(&filename)),
+        ],
+        bytes,
+    )
+        .into_response())
+}
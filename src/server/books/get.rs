@@ -22,7 +22,7 @@ pub async fn get_book(State(pool): State<PgPool>, Path(id): Path<i32>) -> impl I
             (StatusCode::INTERNAL_SERVER_ERROR, Json(Empty))
         }
         Ok(o_book) => match o_book {
-            Some(book) => (StatusCode::OK, Json(GetBook { data: book })),
+            Some(book) => (StatusCode::OK, Json(GetBook { data: Box::new(book) })),
             None => (StatusCode::NOT_FOUND, Json(Empty)),
         },
     }
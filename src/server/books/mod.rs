@@ -1,6 +1,8 @@
 pub use models::book::Book;
+use models::epub::{StyleProfile, TocPosition};
 use serde::{Deserialize, Serialize};
 
+pub mod download;
 pub mod get;
 pub mod update;
 
@@ -10,9 +12,52 @@ pub struct UpdateBook {
     pub cover: Option<String>,
 }
 
+/// Metadata as sent by the book page, subjects separated by commas.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BookMetadataForm {
+    #[serde(default)]
+    pub language: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub subjects: String,
+}
+
+/// Templates of the book's ePubs as sent by the book page, empty ones
+/// falling back to the defaults.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EpubTemplatesForm {
+    #[serde(default)]
+    pub title_page: String,
+    #[serde(default)]
+    pub chapter_header: String,
+    #[serde(default)]
+    pub chapter_footer: String,
+    #[serde(default)]
+    pub colophon: String,
+}
+
+/// Chapters and options of an ePub downloaded directly, the whole book
+/// without a range.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EpubQuery {
+    from: Option<i32>,
+    to: Option<i32>,
+    #[serde(default)]
+    allow_gaps: bool,
+    #[serde(default)]
+    style: StyleProfile,
+    #[serde(default)]
+    grayscale_cover: bool,
+    #[serde(default)]
+    toc_position: TocPosition,
+    #[serde(default)]
+    numbered_toc: bool,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Responses {
-    GetBook { data: Book },
+    GetBook { data: Box<Book> },
     GetBooks { data: Vec<Book> },
     Empty,
 }
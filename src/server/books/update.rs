@@ -2,12 +2,21 @@ use axum::{
     debug_handler,
     extract::{Json, Path, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{Html, IntoResponse, Response},
+    Form,
 };
-use models::book::Book;
+use models::book::{Book, EpubTemplates};
 use sqlx::PgPool;
 
-use super::UpdateBook;
+use crate::{
+    server::{auth::AuthKind, pages::partials::errors::Errors, Error},
+    worker::epub::templates::{unknown_variables, ChapterHeader, Colophon, EpubPage, TitlePage},
+};
+
+use super::{BookMetadataForm, EpubTemplatesForm, UpdateBook};
+
+// a page of markup, not a chapter
+const MAX_TEMPLATE_SIZE: usize = 16 * 1024;
 
 #[allow(dead_code)]
 #[debug_handler]
@@ -47,3 +56,110 @@ pub async fn update_book(
 
     (StatusCode::NOT_FOUND, ())
 }
+
+pub async fn update_book_metadata(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+    Form(input): Form<BookMetadataForm>,
+) -> Result<Response, Error> {
+    auth.human()?;
+    let language = input.language.trim();
+    // BCP 47 tags such as `en` or `zh-Hant`
+    let valid_language = language.len() <= 35
+        && language
+            .split('-')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+    if !language.is_empty() && !valid_language {
+        let message = "Language must be a tag such as en or zh-Hant".to_owned();
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(message)).into_response());
+    }
+    let description = input.description.trim();
+    let subjects = input
+        .subjects
+        .split(',')
+        .map(str::trim)
+        .filter(|subject| !subject.is_empty())
+        .map(str::to_owned)
+        .collect::<Vec<String>>();
+
+    let updated = sqlx::query!(
+        "UPDATE books SET language = $2, description = $3, subjects = $4 WHERE id = $1",
+        id,
+        (!language.is_empty()).then_some(language),
+        (!description.is_empty()).then_some(description),
+        &subjects,
+    )
+    .execute(&pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(Error::NotFound("Book not found".to_owned()));
+    }
+
+    Ok(Html("Metadata saved").into_response())
+}
+
+pub async fn update_book_templates(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+    Form(input): Form<EpubTemplatesForm>,
+) -> Result<Response, Error> {
+    auth.human()?;
+    let templates = [
+        ("title page", input.title_page, TitlePage::VARIABLES),
+        (
+            "chapter header",
+            input.chapter_header,
+            ChapterHeader::VARIABLES,
+        ),
+        // footers know the same as headers
+        (
+            "chapter footer",
+            input.chapter_footer,
+            ChapterHeader::VARIABLES,
+        ),
+        ("colophon", input.colophon, Colophon::VARIABLES),
+    ];
+
+    let mut errors = vec![];
+    let mut templates = templates.map(|(name, template, variables)| {
+        let template = template.trim();
+        if template.len() > MAX_TEMPLATE_SIZE {
+            errors.push(format!(
+                "The {name} is limited to {} KB",
+                MAX_TEMPLATE_SIZE / 1024
+            ));
+        }
+        for unknown in unknown_variables(template, variables) {
+            errors.push(format!(
+                "The {name} has no {{{{ {unknown} }}}}, only {}",
+                variables.join(", ")
+            ));
+        }
+
+        (!template.is_empty()).then(|| template.to_owned())
+    });
+    if !errors.is_empty() {
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Errors { errors }).into_response());
+    }
+
+    let templates = EpubTemplates {
+        title_page: templates[0].take(),
+        chapter_header: templates[1].take(),
+        chapter_footer: templates[2].take(),
+        colophon: templates[3].take(),
+    };
+    let updated = sqlx::query!(
+        "UPDATE books SET epub_templates = $2 WHERE id = $1",
+        id,
+        serde_json::to_value(templates).unwrap(),
+    )
+    .execute(&pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(Error::NotFound("Book not found".to_owned()));
+    }
+
+    Ok(Html("Templates saved").into_response())
+}
@@ -4,7 +4,7 @@ use sqlx::PgPool;
 
 use crate::server::{auth::AuthKind, Error};
 
-use super::{AddChapter, Responses};
+use super::{content::sanitize, AddChapter, Footnote, Footnotes, Responses};
 
 #[debug_handler]
 pub async fn add_chapter(
@@ -27,10 +27,14 @@ pub async fn add_chapter(
     if o_book.is_none() {
         println!("Inserting new book: {}", input.book);
         sqlx::query!(
-            "INSERT INTO books (name, author, translator) VALUES ($1, $2, $3)",
+            "INSERT INTO books (name, author, translator, language, description, subjects)
+            VALUES ($1, $2, $3, $4, $5, $6)",
             input.book,
             input.author,
             input.translator,
+            input.language,
+            input.description,
+            &input.subjects,
         )
         .execute(&pool)
         .await
@@ -47,17 +51,38 @@ pub async fn add_chapter(
     let book = o_book.expect("Book should exist");
 
     println!("Inserting new chapter: {}", input);
+    let content = match input.html {
+        true => sanitize(&input.content),
+        false => input.content,
+    };
+    let footnotes: Vec<Footnote> = input
+        .footnotes
+        .into_iter()
+        .map(|footnote| match input.html {
+            true => Footnote {
+                content: sanitize(&footnote.content),
+                ..footnote
+            },
+            false => footnote,
+        })
+        .collect();
     if (sqlx::query!(
         "INSERT INTO chapters (
             book_id,
             name,
             content,
-            number_in_book
-            ) VALUES ($1, $2, $3, $4)",
+            number_in_book,
+            html,
+            footnotes,
+            url
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)",
         book.id,
         input.name,
-        input.content,
+        content,
         input.number_in_book,
+        input.html,
+        serde_json::to_value(Footnotes(footnotes)).unwrap(),
+        input.url,
     )
     .execute(&pool)
     .await)
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+use ammonia::Builder;
+
+// formatting translators use, nothing that runs, tracks or restyles
+const TAGS: [&str; 34] = [
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "code",
+    "del",
+    "em",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "s",
+    "small",
+    "strong",
+    "sub",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+
+/// Cleans chapter HTML sent to `POST /chapter`: scripts, styles, tracking
+/// and event attributes, and anything outside of basic formatting is
+/// removed.
+pub fn sanitize(html: &str) -> String {
+    builder()
+        .tags(TAGS.into_iter().collect())
+        .clean(html)
+        .to_string()
+}
+
+/// Cleans the ePub templates of a book, laying out pages on top of
+/// formatting: titles, sections and classes for custom CSS are kept too.
+pub fn sanitize_template(html: &str) -> String {
+    let mut tags: HashSet<&str> = TAGS.into_iter().collect();
+    tags.extend(["div", "h1", "h2", "section", "span"]);
+
+    builder()
+        .tags(tags)
+        .add_generic_attributes(["class"])
+        .clean(html)
+        .to_string()
+}
+
+fn builder() -> Builder<'static> {
+    let tag_attributes = HashMap::from([
+        ("a", HashSet::from(["href", "title"])),
+        ("abbr", HashSet::from(["title"])),
+        (
+            "img",
+            HashSet::from(["src", "alt", "title", "width", "height"]),
+        ),
+        ("td", HashSet::from(["colspan", "rowspan"])),
+        ("th", HashSet::from(["colspan", "rowspan"])),
+    ]);
+
+    let mut builder = Builder::empty();
+    builder
+        .tag_attributes(tag_attributes)
+        .url_schemes(HashSet::from(["http", "https"]))
+        .link_rel(Some("noopener noreferrer"));
+
+    builder
+}
+
+/// Turns chapters stored as text, paragraphs joined with a bare `<p>` by the
+/// boost script, into HTML paragraphs.
+///
+/// The text is escaped, and line breaks inside a paragraph are kept.
+pub fn text_to_html(content: &str) -> String {
+    let lowercase = content.to_ascii_lowercase();
+    let mut paragraphs = String::new();
+    let mut start = 0;
+
+    // `<p>` and `</p>` are ASCII, so indexes match between both strings
+    let mut push = |text: &str| {
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(escape)
+            .collect::<Vec<String>>();
+        if !lines.is_empty() {
+            paragraphs.push_str(&format!("<p>{}</p>", lines.join("<br/>")));
+        }
+    };
+    while let Some((index, tag)) = ["<p>", "</p>"]
+        .iter()
+        .filter_map(|tag| {
+            lowercase[start..]
+                .find(tag)
+                .map(|index| (start + index, tag))
+        })
+        .min()
+    {
+        push(&content[start..index]);
+        start = index + tag.len();
+    }
+    push(&content[start..]);
+
+    paragraphs
+}
+
+/// Chapter content as HTML, whichever way it was stored.
+pub fn to_html(content: &str, html: bool) -> String {
+    match html {
+        true => content.to_owned(),
+        false => text_to_html(content),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paragraphs_split_on_p_tags() {
+        assert_eq!(
+            text_to_html("First<p>Second</P>Third"),
+            "<p>First</p><p>Second</p><p>Third</p>"
+        );
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            text_to_html("Fish & chips<p>1 < 2 > 0"),
+            "<p>Fish &amp; chips</p><p>1 &lt; 2 &gt; 0</p>"
+        );
+    }
+
+    #[test]
+    fn other_tags_are_text() {
+        assert_eq!(
+            text_to_html("<b>bold<p><i>unclosed"),
+            "<p>&lt;b&gt;bold</p><p>&lt;i&gt;unclosed</p>"
+        );
+    }
+
+    #[test]
+    fn unclosed_paragraph() {
+        assert_eq!(text_to_html("<p>Start"), "<p>Start</p>");
+    }
+
+    #[test]
+    fn line_breaks_are_kept_and_empty_lines_dropped() {
+        assert_eq!(
+            text_to_html("  One\n\n  Two  \r\n<p>\n<p>   <p>Three"),
+            "<p>One<br/>Two</p><p>Three</p>"
+        );
+    }
+
+    #[test]
+    fn empty_text() {
+        assert_eq!(text_to_html(""), "");
+        assert_eq!(text_to_html("<p></p>\n"), "");
+    }
+}
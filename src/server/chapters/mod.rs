@@ -1,7 +1,8 @@
 pub mod add;
+pub mod content;
 pub mod get;
 
-pub use models::chapter::Chapter;
+pub use models::chapter::{Chapter, Footnote, Footnotes};
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
@@ -22,6 +23,19 @@ pub struct AddChapter {
     number_in_book: i32,
     author: Option<String>,
     translator: Option<String>,
+    // sanitized HTML, instead of text paragraphs joined with `<p>`
+    #[serde(default)]
+    html: bool,
+    // referenced from the content, see [`Footnotes`]
+    #[serde(default)]
+    footnotes: Vec<Footnote>,
+    // page the chapter was read on
+    url: Option<String>,
+    // only used when the book is created
+    language: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    subjects: Vec<String>,
 }
 
 impl Display for AddChapter {
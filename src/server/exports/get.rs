@@ -0,0 +1,186 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use models::{artifact::StoredArtifact, delivery::DeliveryStatus, export::Export};
+use sqlx::PgPool;
+
+use crate::server::{auth::AuthKind, Error};
+
+use super::{
+    DownloadQuery, ExportStatus, ExportsQuery,
+    Responses::{GetExport, GetExports},
+};
+
+pub async fn get_exports(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Query(query): Query<ExportsQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let exports = fetch_exports(&pool, auth.user().id, query.book_id)
+        .await?
+        .into_iter()
+        .filter(|export| match &query.state {
+            Some(state) => &export.get_state() == state,
+            None => true,
+        })
+        .collect();
+
+    let data = with_status(&pool, exports).await?;
+
+    Ok((StatusCode::OK, Json(GetExports { data })))
+}
+
+pub async fn get_export(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, Error> {
+    let export = sqlx::query_as!(
+        Export,
+        "SELECT * FROM exports WHERE id = $1 AND user_id = $2",
+        id,
+        auth.user().id,
+    )
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::NotFound("export not found".to_owned()))?;
+
+    let data = with_status(&pool, vec![export])
+        .await?
+        .pop()
+        .expect("export should still be there");
+
+    Ok((
+        StatusCode::OK,
+        Json(GetExport {
+            data: Box::new(data),
+        }),
+    ))
+}
+
+/// The user's exports touching the given book, or all of them, newest first.
+pub async fn fetch_exports(
+    pool: &PgPool,
+    user_id: i32,
+    book_id: Option<i32>,
+) -> Result<Vec<Export>, sqlx::Error> {
+    sqlx::query_as!(
+        Export,
+        "SELECT * FROM exports e
+        WHERE e.user_id = $2
+            AND (
+                $1::int IS NULL
+                OR (e.meta->'ChaptersRange'->>'book_id')::int = $1
+                OR (e.meta->>'FullBook')::int = $1
+                OR (e.meta->>'SinceLastExport')::int = $1
+                OR (e.meta->>'SingleChapter')::int IN (SELECT id FROM chapters WHERE book_id = $1)
+                OR e.meta->'Anthology'->'parts' @> jsonb_build_array(jsonb_build_object('book_id', $1))
+            )
+        ORDER BY e.created_at DESC",
+        book_id,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn with_status(
+    pool: &PgPool,
+    exports: Vec<Export>,
+) -> Result<Vec<ExportStatus>, sqlx::Error> {
+    let ids: Vec<i32> = exports.iter().map(|export| export.id).collect();
+    let mut deliveries = sqlx::query_as!(
+        DeliveryStatus,
+        "SELECT * FROM deliveries WHERE export_id = ANY($1) ORDER BY volume ASC, id ASC",
+        &ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut artifacts = sqlx::query_as!(
+        StoredArtifact,
+        "SELECT id, export_id, volume, filename, size, checksum, created_at
+        FROM artifacts
+        WHERE export_id = ANY($1)
+        ORDER BY volume ASC",
+        &ids,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(exports
+        .into_iter()
+        .map(|export| {
+            let (own, others) = deliveries
+                .drain(..)
+                .partition(|delivery| delivery.export_id == export.id);
+            deliveries = others;
+            let (own_artifacts, other_artifacts) = artifacts
+                .drain(..)
+                .partition(|artifact| artifact.export_id == export.id);
+            artifacts = other_artifacts;
+
+            ExportStatus {
+                state: export.get_state(),
+                export,
+                deliveries: own,
+                artifacts: own_artifacts,
+            }
+        })
+        .collect())
+}
+
+pub async fn download_export(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+    Query(query): Query<DownloadQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let user = auth.human()?;
+
+    let artifact = sqlx::query!(
+        "SELECT a.filename, a.content
+        FROM artifacts a
+        JOIN exports e ON e.id = a.export_id
+        WHERE a.export_id = $1 AND a.volume = $2 AND e.user_id = $3",
+        id,
+        query.volume.unwrap_or(1),
+        user.id,
+    )
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::NotFound("export file not found".to_owned()))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/epub+zip".to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&artifact.filename),
+            ),
+        ],
+        artifact.content,
+    ))
+}
+
+// titles are rarely ascii only, so the name is also given encoded as per RFC 6266
+pub fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
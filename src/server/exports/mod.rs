@@ -1,16 +1,90 @@
 pub mod add;
-mod epub;
+pub mod get;
+pub mod resend;
+pub mod validate;
 
+use models::{
+    artifact::StoredArtifact,
+    delivery::DeliveryStatus,
+    epub::{StyleProfile, TocPosition},
+    export::{AnthologyPart, Export, ExportState},
+};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
-pub enum Responses {
-    Empty,
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    #[default]
+    ChaptersRange,
+    FullBook,
+    SingleChapter,
+    SinceLastExport,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AddToQueue {
+    #[serde(default)]
+    kind: Kind,
     book_id: i32,
-    from: i32,
-    to: i32,
+    // chapter numbers, `from` alone is used for a single chapter
+    from: Option<i32>,
+    to: Option<i32>,
+    // unchecked boxes are left out of the form
+    #[serde(default)]
+    allow_gaps: bool,
+    // left empty to only split by size
+    #[serde(default)]
+    max_chapters: String,
+    #[serde(default)]
+    style: StyleProfile,
+    #[serde(default)]
+    grayscale_cover: bool,
+    #[serde(default)]
+    toc_position: TocPosition,
+    #[serde(default)]
+    numbered_toc: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AddAnthology {
+    title: String,
+    parts: Vec<AnthologyPart>,
+    #[serde(default)]
+    allow_gaps: bool,
+    max_chapters: Option<i32>,
+    #[serde(default)]
+    style: StyleProfile,
+    #[serde(default)]
+    grayscale_cover: bool,
+    #[serde(default)]
+    toc_position: TocPosition,
+    #[serde(default)]
+    numbered_toc: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DownloadQuery {
+    volume: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportsQuery {
+    book_id: Option<i32>,
+    state: Option<ExportState>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportStatus {
+    #[serde(flatten)]
+    pub export: Export,
+    pub state: ExportState,
+    pub deliveries: Vec<DeliveryStatus>,
+    // one per volume, gone once past their retention
+    pub artifacts: Vec<StoredArtifact>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum Responses {
+    GetExports { data: Vec<ExportStatus> },
+    GetExport { data: Box<ExportStatus> },
 }
@@ -0,0 +1,73 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse},
+};
+use models::export::{Export, ExportState};
+use sqlx::PgPool;
+
+use crate::server::{auth::AuthKind, Error};
+
+/// Queues an export to be delivered again, from the ePub generated the
+/// first time.
+pub async fn resend_export(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, Error> {
+    let user = auth.human()?;
+
+    let export = sqlx::query_as!(
+        Export,
+        "SELECT * FROM exports WHERE id = $1 AND user_id = $2",
+        id,
+        user.id,
+    )
+    .fetch_optional(&pool)
+    .await?
+    .ok_or(Error::NotFound("export not found".to_owned()))?;
+
+    // checked again when resetting it, the worker or the purge may get to
+    // it in between
+    let message = match export.get_state() {
+        ExportState::Created | ExportState::Processing => "Export is not done yet",
+        ExportState::Processed if export.processing_started_at.is_some() => {
+            "Export is still being sent"
+        }
+        _ => "Export file is gone, export it again",
+    };
+
+    // the worker picks it up again, finds the stored artifact and skips
+    // straight to the deliveries
+    let mut tx = pool.begin().await?;
+    let reset = sqlx::query!(
+        "UPDATE exports
+        SET processing_started_at = NULL,
+            heartbeat_at = NULL,
+            processed_at = NULL,
+            next_attempt_at = NULL,
+            attempts = 0,
+            sent = false,
+            error = NULL
+        WHERE id = $1
+            AND user_id = $2
+            AND processed_at IS NOT NULL
+            AND (processing_started_at IS NULL OR sent OR error IS NOT NULL)
+            AND EXISTS (SELECT 1 FROM artifacts WHERE export_id = $1)
+        RETURNING id",
+        id,
+        user.id,
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if reset.is_none() {
+        return Ok((StatusCode::CONFLICT, Html(message.to_owned())));
+    }
+    sqlx::query!("DELETE FROM deliveries WHERE export_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    tracing::info!(export_id = id, "Export queued to be sent again");
+    Ok((StatusCode::OK, Html("Queued to be sent again".to_owned())))
+}
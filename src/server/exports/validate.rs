@@ -0,0 +1,264 @@
+use std::fmt::Display;
+
+use axum::{http::StatusCode, Json};
+use models::export::{ExportKinds, ExportOptions};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+/// A reason an export can't be made, checked before queuing it.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Invalid {
+    EmptyAnthology,
+    VolumeTooSmall {
+        max_chapters: i32,
+    },
+    UnknownBook {
+        book_id: i32,
+    },
+    ReversedRange {
+        book_id: i32,
+        from: i32,
+        to: i32,
+    },
+    NoChapters {
+        book_id: i32,
+    },
+    // inclusive ranges of chapter numbers
+    MissingChapters {
+        book_id: i32,
+        missing: Vec<(i32, i32)>,
+    },
+    // only known once generated, when downloading it directly
+    TooBig {
+        max_size: usize,
+    },
+}
+
+impl Display for Invalid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Invalid::EmptyAnthology => write!(f, "An anthology needs at least one part"),
+            Invalid::VolumeTooSmall { max_chapters } => {
+                write!(f, "Volumes need at least 1 chapter, not {max_chapters}")
+            }
+            Invalid::UnknownBook { book_id } => write!(f, "Book {book_id} does not exist"),
+            Invalid::ReversedRange { book_id, from, to } => write!(
+                f,
+                "Book {book_id}: the range starts at {from}, after its end {to}"
+            ),
+            Invalid::NoChapters { book_id } => write!(f, "Book {book_id}: no chapters to export"),
+            Invalid::MissingChapters { book_id, missing } => {
+                let missing = missing
+                    .iter()
+                    .map(|(from, to)| match from == to {
+                        true => from.to_string(),
+                        false => format!("{from}-{to}"),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "Book {book_id}: missing chapters {missing}")
+            }
+            Invalid::TooBig { .. } => {
+                write!(f, "Too big to be downloaded directly, export it instead")
+            }
+        }
+    }
+}
+
+/// What clients get back for each problem: its kind and fields, along with a
+/// message to show.
+#[derive(Debug, Serialize)]
+pub struct Problem<'a> {
+    #[serde(flatten)]
+    invalid: &'a Invalid,
+    message: String,
+}
+
+/// The body of a rejected export, `{ "errors": [...] }`, however it was
+/// queued.
+pub fn rejected(invalid: &[Invalid]) -> (StatusCode, Json<Value>) {
+    let problems: Vec<Problem> = invalid
+        .iter()
+        .map(|invalid| Problem {
+            invalid,
+            message: invalid.to_string(),
+        })
+        .collect();
+
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({ "errors": problems })),
+    )
+}
+
+/// Lists everything wrong with an export, empty when it can be queued.
+pub async fn validate(
+    pool: &PgPool,
+    kind: &ExportKinds,
+    options: &ExportOptions,
+) -> Result<Vec<Invalid>, sqlx::Error> {
+    let mut invalid = vec![];
+    if let Some(max_chapters) = options.max_chapters.filter(|max| *max < 1) {
+        invalid.push(Invalid::VolumeTooSmall { max_chapters });
+    }
+
+    let parts = match kind {
+        ExportKinds::ChaptersRange { book_id, chapters } => vec![(*book_id, Some(*chapters))],
+        ExportKinds::FullBook(book_id) => vec![(*book_id, None)],
+        // the chapter was looked up when building the export
+        ExportKinds::SingleChapter(_) => vec![],
+        // the chapters are only picked when the export is processed
+        ExportKinds::SinceLastExport(book_id) => {
+            invalid.extend(unknown_book(pool, *book_id).await?);
+            vec![]
+        }
+        ExportKinds::Anthology { parts, .. } => {
+            if parts.is_empty() {
+                invalid.push(Invalid::EmptyAnthology);
+            }
+            parts
+                .iter()
+                .map(|part| (part.book_id, part.chapters))
+                .collect()
+        }
+    };
+
+    for (book_id, range) in parts {
+        if let Some(problem) = validate_part(pool, book_id, range, options).await? {
+            invalid.push(problem);
+        }
+    }
+
+    Ok(invalid)
+}
+
+async fn validate_part(
+    pool: &PgPool,
+    book_id: i32,
+    range: Option<(i32, i32)>,
+    options: &ExportOptions,
+) -> Result<Option<Invalid>, sqlx::Error> {
+    if let Some((from, to)) = range.filter(|(from, to)| from > to) {
+        return Ok(Some(Invalid::ReversedRange { book_id, from, to }));
+    }
+
+    if let Some(unknown) = unknown_book(pool, book_id).await? {
+        return Ok(Some(unknown));
+    }
+
+    let (from, to) = range.unwrap_or((i32::MIN, i32::MAX));
+    let numbers = sqlx::query_scalar!(
+        "SELECT number_in_book FROM chapters
+        WHERE book_id = $1
+            AND number_in_book >= $2
+            AND number_in_book <= $3
+        ORDER BY number_in_book ASC",
+        book_id,
+        from,
+        to,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let (Some(first), Some(last)) = (numbers.first(), numbers.last()) else {
+        return Ok(Some(Invalid::NoChapters { book_id }));
+    };
+    if options.allow_gaps {
+        return Ok(None);
+    }
+
+    // a whole book goes from its first chapter to its last one
+    let missing = gaps(&numbers, range.unwrap_or((*first, *last)));
+    Ok((!missing.is_empty()).then_some(Invalid::MissingChapters { book_id, missing }))
+}
+
+async fn unknown_book(pool: &PgPool, book_id: i32) -> Result<Option<Invalid>, sqlx::Error> {
+    let book = sqlx::query!("SELECT id FROM books WHERE id = $1", book_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(book.is_none().then_some(Invalid::UnknownBook { book_id }))
+}
+
+/// Chapter numbers of `range` absent from the sorted `numbers`, as
+/// inclusive ranges.
+pub fn gaps(numbers: &[i32], (from, to): (i32, i32)) -> Vec<(i32, i32)> {
+    let mut missing = vec![];
+    // wider than the chapter numbers, so it can't overflow after i32::MAX
+    let mut next = i64::from(from);
+
+    for &number in numbers {
+        if i64::from(number) > next {
+            missing.push((next as i32, number - 1));
+        }
+        next = next.max(i64::from(number) + 1);
+    }
+    if next <= i64::from(to) {
+        missing.push((next as i32, to));
+    }
+
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_gaps() {
+        assert_eq!(gaps(&[1, 2, 3], (1, 3)), vec![]);
+    }
+
+    #[test]
+    fn gaps_in_the_middle() {
+        assert_eq!(gaps(&[1, 2, 5, 7], (1, 7)), vec![(3, 4), (6, 6)]);
+    }
+
+    #[test]
+    fn gaps_at_both_ends() {
+        assert_eq!(gaps(&[3, 4], (1, 6)), vec![(1, 2), (5, 6)]);
+    }
+
+    #[test]
+    fn every_chapter_missing() {
+        assert_eq!(gaps(&[], (2, 4)), vec![(2, 4)]);
+    }
+
+    #[test]
+    fn duplicated_numbers_are_not_gaps() {
+        assert_eq!(gaps(&[1, 2, 2, 3], (1, 3)), vec![]);
+    }
+
+    #[test]
+    fn gaps_up_to_the_largest_number() {
+        assert_eq!(
+            gaps(&[i32::MAX - 2], (i32::MAX - 2, i32::MAX)),
+            vec![(i32::MAX - 1, i32::MAX)]
+        );
+        assert_eq!(
+            gaps(&[i32::MAX], (i32::MAX - 1, i32::MAX)),
+            vec![(i32::MAX - 1, i32::MAX - 1)]
+        );
+    }
+
+    #[test]
+    fn problems_carry_their_fields_and_message() {
+        let (status, body) = rejected(&[Invalid::MissingChapters {
+            book_id: 1,
+            missing: vec![(3, 4), (6, 6)],
+        }]);
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body.0,
+            json!({ "errors": [{
+                "kind": "missing_chapters",
+                "book_id": 1,
+                "missing": [[3, 4], [6, 6]],
+                "message": "Book 1: missing chapters 3-4, 6",
+            }] })
+        );
+    }
+}
@@ -4,14 +4,28 @@ pub mod chapters;
 pub mod exports;
 pub mod health;
 pub mod pages;
+pub mod styles;
+pub mod subscriptions;
 
 use self::{
     auth::{callback::login_callback, cookie::get_cookie, logout::logout, AuthKind},
+    books::{
+        download::download_epub,
+        update::{update_book_metadata, update_book_templates},
+    },
     chapters::{add::add_chapter, get::get_chapters},
-    exports::add::add_to_queue,
+    exports::{
+        add::{add_anthology_to_queue, add_to_queue},
+        get::{download_export, get_export, get_exports},
+        resend::resend_export,
+    },
     health::health,
+    styles::update::{update_book_css, update_user_css},
+    subscriptions::{
+        add::add_subscription, delete::delete_subscription, update::update_subscription,
+    },
 };
-use super::{env::Environment, pool};
+use super::{env::Environment, pool, signal::shutdown_signal};
 use askama::Template;
 use axum::{
     async_trait,
@@ -22,34 +36,35 @@ use axum::{
         header, request::Parts, HeaderMap, HeaderName, HeaderValue, Method, Response, StatusCode,
     },
     response::{Html, IntoResponse, Redirect},
-    routing::{get, post},
+    routing::{get, post, put},
     Router,
 };
 use models::user::User;
 use sqlx::PgPool;
 use std::{net::SocketAddr, time::Duration};
-use tokio::signal;
 use tower::{BoxError, ServiceBuilder};
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 pub async fn start(env: Environment) {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "wuxia2kindle=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
     let pool = pool::mk_pool(env.database_url.clone()).await;
     let app_state = AppState {
         pool,
         environment: env.clone(),
     };
 
+    // generating an ePub takes longer than answering the other requests
+    let downloads = Router::new()
+        .route("/book/:id/epub", get(download_epub))
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(handle_error))
+                .timeout(Duration::from_secs(60))
+                .layer(TraceLayer::new_for_http())
+                .into_inner(),
+        );
+
     // build our application with a route
     let app = Router::new()
         .route("/login", get(pages::login::login))
@@ -58,9 +73,22 @@ pub async fn start(env: Environment) {
         .route("/books", get(pages::partials::books::books))
         .route("/book/:id", get(pages::book::book))
         .route("/book/:id/cover", get(pages::partials::cover::cover))
+        .route("/book/:id/exports", get(pages::partials::exports::exports))
+        .route("/book/:id/css", put(update_book_css))
+        .route("/book/:id/metadata", put(update_book_metadata))
+        .route("/book/:id/templates", put(update_book_templates))
         .route("/chapter/:id", get(pages::chapter::chapter))
         .route("/settings", get(pages::settings::settings))
+        .route("/settings/css", put(update_user_css))
         .route("/token", get(pages::partials::token::get_token))
+        .route(
+            "/subscriptions",
+            get(pages::partials::subscriptions::subscriptions).post(add_subscription),
+        )
+        .route(
+            "/subscriptions/:id",
+            put(update_subscription).delete(delete_subscription),
+        )
         // misc
         .route("/health", get(health))
         // auth
@@ -75,6 +103,11 @@ pub async fn start(env: Environment) {
         // .route("/book/:id", get(get_book).patch(update_book))
         .route("/book/:id/chapters", get(get_chapters))
         .route("/export", post(add_to_queue))
+        .route("/export/anthology", post(add_anthology_to_queue))
+        .route("/exports", get(get_exports))
+        .route("/exports/:id", get(get_export))
+        .route("/exports/:id/epub", get(download_export))
+        .route("/exports/:id/resend", post(resend_export))
         .route("/*catchall", get(not_found))
         .layer(
             CorsLayer::new()
@@ -94,20 +127,12 @@ pub async fn start(env: Environment) {
         )
         .layer(
             ServiceBuilder::new()
-                .layer(HandleErrorLayer::new(|error: BoxError| async move {
-                    if error.is::<tower::timeout::error::Elapsed>() {
-                        Ok(StatusCode::REQUEST_TIMEOUT)
-                    } else {
-                        Err((
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Unhandled internal error: {}", error),
-                        ))
-                    }
-                }))
+                .layer(HandleErrorLayer::new(handle_error))
                 .timeout(Duration::from_secs(5))
                 .layer(TraceLayer::new_for_http())
                 .into_inner(),
         )
+        .merge(downloads)
         .layer(DefaultBodyLimit::max(5_242_880))
         .with_state(app_state);
 
@@ -120,32 +145,6 @@ pub async fn start(env: Environment) {
         .unwrap();
 }
 
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("failed to install Ctrl+C handler");
-    };
-
-    #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
-    };
-
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
-
-    tokio::select! {
-        _ = ctrl_c => {},
-        _ = terminate => {},
-    }
-    println!("Shutting down gracefully...");
-    // pool.close().await;
-}
-
 async fn not_found() -> Result<Html<String>, Error> {
     Err(Error::NotFound("Page not found".to_string()))
 }
@@ -179,6 +178,17 @@ pub enum Error {
     Forbidden,
 }
 
+async fn handle_error(error: BoxError) -> Result<StatusCode, (StatusCode, String)> {
+    if error.is::<tower::timeout::error::Elapsed>() {
+        Ok(StatusCode::REQUEST_TIMEOUT)
+    } else {
+        Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Unhandled internal error: {}", error),
+        ))
+    }
+}
+
 #[derive(Template)]
 #[template(path = "404.html")]
 pub struct NotFoundTemplate {
@@ -235,6 +245,7 @@ where
 #[allow(dead_code)]
 struct DatabaseConnection(sqlx::pool::PoolConnection<sqlx::Postgres>);
 
+#[allow(dead_code)]
 fn sqlx_error_mapper(e: sqlx::Error) -> Error {
     tracing::error!("SQLx error: {:#}", e);
     Error::AppError(e.into())
@@ -265,6 +276,13 @@ impl AuthKind {
         }
     }
 
+    /// The user behind the request, whether it comes from a browser or a token.
+    pub fn user(&self) -> &User {
+        match self {
+            Self::Human(user) | Self::Machine(user) => user,
+        }
+    }
+
     pub fn machine(&self) -> Result<&User, Error> {
         match self {
             Self::Human(_) => Err(Error::Forbidden),
@@ -1,9 +1,13 @@
 use anyhow::Result;
 use askama::Template;
 use axum::extract::{Path, State};
+use models::book::EpubTemplates;
 use sqlx::PgPool;
 
-use crate::server::{auth::AuthKind, Error};
+use crate::{
+    server::{auth::AuthKind, Error},
+    worker::epub::templates::{ChapterHeader, Colophon, EpubPage, TitlePage},
+};
 
 struct NoCoverBook {
     id: i32,
@@ -11,6 +15,10 @@ struct NoCoverBook {
     chapter_count: Option<i32>,
     author: Option<String>,
     translator: Option<String>,
+    language: String,
+    description: String,
+    // comma separated, as edited
+    subjects: String,
 }
 
 #[derive(Clone)]
@@ -26,6 +34,10 @@ pub struct BookAndChaptersTemplate {
     book: NoCoverBook,
     chapters: Vec<Chapter>,
     reverse: fn(Vec<Chapter>) -> Vec<Chapter>,
+    custom_css: String,
+    templates: EpubTemplates,
+    // last chapter the user received, where "since last export" starts
+    watermark: Option<i32>,
 }
 
 #[derive(Debug)]
@@ -35,6 +47,11 @@ struct BookAndChaptersQuery {
     chapter_count: Option<i32>,
     author: Option<String>,
     translator: Option<String>,
+    custom_css: Option<String>,
+    epub_templates: EpubTemplates,
+    language: Option<String>,
+    description: Option<String>,
+    subjects: Vec<String>,
     chapter_id: Option<i32>,
     chapter_name: Option<String>,
     chapter_number: i32,
@@ -45,9 +62,7 @@ pub async fn book(
     State(pool): State<PgPool>,
     Path(book_id): Path<i32>,
 ) -> Result<BookAndChaptersTemplate, Error> {
-    if let Err(error) = auth.human() {
-        return Err(error);
-    }
+    let user = auth.human()?;
 
     let response = sqlx::query_as!(
         BookAndChaptersQuery,
@@ -58,6 +73,11 @@ pub async fn book(
             b.chapter_count chapter_count,
             b.author author,
             b.translator translator,
+            b.custom_css custom_css,
+            b.epub_templates epub_templates,
+            b.language language,
+            b.description description,
+            b.subjects subjects,
             c.id chapter_id,
             c.name chapter_name,
             c.number_in_book chapter_number
@@ -81,7 +101,12 @@ pub async fn book(
         chapter_count: raw_book.chapter_count,
         author: raw_book.author.to_owned(),
         translator: raw_book.translator.to_owned(),
+        language: raw_book.language.clone().unwrap_or_default(),
+        description: raw_book.description.clone().unwrap_or_default(),
+        subjects: raw_book.subjects.join(", "),
     };
+    let custom_css = raw_book.custom_css.clone().unwrap_or_default();
+    let templates = raw_book.epub_templates.clone();
 
     let chapters: Vec<Chapter> = response
         .iter()
@@ -102,6 +127,14 @@ pub async fn book(
         })
         .collect();
 
+    let watermark = sqlx::query_scalar!(
+        "SELECT last_chapter FROM watermarks WHERE user_id = $1 AND book_id = $2",
+        user.id,
+        book_id,
+    )
+    .fetch_optional(&pool)
+    .await?;
+
     let reverse = |chapters: Vec<Chapter>| {
         let mut rev_chapters = chapters.clone();
         rev_chapters.sort_by(|a, b| b.number.cmp(&a.number));
@@ -112,5 +145,59 @@ pub async fn book(
         book,
         chapters,
         reverse,
+        custom_css,
+        templates,
+        watermark,
     })
 }
+
+// a textarea of the ePub templates form
+struct EpubTemplateField {
+    name: &'static str,
+    label: &'static str,
+    // what it can use
+    variables: String,
+    markup: String,
+}
+
+impl BookAndChaptersTemplate {
+    fn epub_templates(&self) -> [EpubTemplateField; 4] {
+        let field = |name, label, variables: &[&str], markup: &Option<String>| EpubTemplateField {
+            name,
+            label,
+            variables: variables
+                .iter()
+                .map(|name| format!("{{{{ {name} }}}}"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            markup: markup.clone().unwrap_or_default(),
+        };
+
+        [
+            field(
+                "title_page",
+                "Title page",
+                TitlePage::VARIABLES,
+                &self.templates.title_page,
+            ),
+            field(
+                "chapter_header",
+                "Chapter header",
+                ChapterHeader::VARIABLES,
+                &self.templates.chapter_header,
+            ),
+            field(
+                "chapter_footer",
+                "Chapter footer",
+                ChapterHeader::VARIABLES,
+                &self.templates.chapter_footer,
+            ),
+            field(
+                "colophon",
+                "About this export",
+                Colophon::VARIABLES,
+                &self.templates.colophon,
+            ),
+        ]
+    }
+}
@@ -2,18 +2,25 @@ use askama::Template;
 use axum::extract::{Path, State};
 use sqlx::PgPool;
 
-use crate::server::{auth::AuthKind, Error};
+use crate::server::{
+    auth::AuthKind,
+    chapters::{content::to_html, Footnote, Footnotes},
+    Error,
+};
 
 #[derive(Template)]
 #[template(path = "chapter.html")]
 pub struct ChapterTemplate {
     pub name: String,
     pub content: String,
+    pub footnotes: Vec<Footnote>,
 }
 
 struct ChapterQuery {
     pub name: String,
     pub content: String,
+    pub html: bool,
+    pub footnotes: Footnotes,
 }
 
 pub async fn chapter(
@@ -27,7 +34,7 @@ pub async fn chapter(
 
     let chapter = sqlx::query_as!(
         ChapterQuery,
-        "SELECT name, content FROM chapters WHERE id = $1",
+        "SELECT name, content, html, footnotes FROM chapters WHERE id = $1",
         id
     )
     .fetch_optional(&pool)
@@ -42,7 +49,16 @@ pub async fn chapter(
             None => Err(Error::NotFound("chapter not found".to_owned())),
             Some(chapter) => Ok(ChapterTemplate {
                 name: chapter.name,
-                content: chapter.content,
+                content: to_html(&chapter.content, chapter.html),
+                footnotes: chapter
+                    .footnotes
+                    .0
+                    .into_iter()
+                    .map(|footnote| Footnote {
+                        content: to_html(&footnote.content, chapter.html),
+                        ..footnote
+                    })
+                    .collect(),
             }),
         },
     }
@@ -0,0 +1,11 @@
+use askama::Template;
+
+/// Messages shown under a form, one per line.
+///
+/// They can quote what was submitted, so they are escaped like any other
+/// template value.
+#[derive(Template)]
+#[template(path = "partials/errors.html")]
+pub struct Errors {
+    pub errors: Vec<String>,
+}
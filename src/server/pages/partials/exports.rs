@@ -0,0 +1,128 @@
+use anyhow::Result;
+use askama::Template;
+use axum::extract::{Path, State};
+use chrono::{DateTime, Utc};
+use models::export::ExportState;
+use sqlx::PgPool;
+
+use crate::server::{
+    auth::AuthKind,
+    exports::get::{fetch_exports, with_status},
+    Error,
+};
+
+// the panel only shows the latest exports, the API has the rest
+const LIMIT: usize = 20;
+
+pub struct ExportRow {
+    id: i32,
+    description: String,
+    state: String,
+    error: Option<String>,
+    created_at: String,
+    // time spent in the queue before a worker picked it up
+    waited: Option<String>,
+    // time spent generating it
+    took: Option<String>,
+    deliveries: Vec<DeliveryRow>,
+    // stored ePubs, while they can still be downloaded
+    files: Vec<FileRow>,
+    // only finished exports can be sent again
+    done: bool,
+}
+
+pub struct FileRow {
+    volume: i32,
+    size: String,
+}
+
+pub struct DeliveryRow {
+    destination: String,
+    // only shown for exports split in volumes
+    volume: Option<i32>,
+    state: String,
+    error: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "partials/exports.html")]
+pub struct Exports {
+    exports: Vec<ExportRow>,
+}
+
+pub async fn exports(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(book_id): Path<i32>,
+) -> Result<Exports, Error> {
+    let user = auth.human()?;
+
+    let mut exports = fetch_exports(&pool, user.id, Some(book_id)).await?;
+    exports.truncate(LIMIT);
+
+    let exports = with_status(&pool, exports)
+        .await?
+        .into_iter()
+        .map(|e| ExportRow {
+            id: e.export.id,
+            files: e
+                .artifacts
+                .iter()
+                .map(|artifact| FileRow {
+                    volume: artifact.volume,
+                    size: human_size(artifact.size),
+                })
+                .collect(),
+            done: !matches!(e.state, ExportState::Created | ExportState::Processing),
+            description: e.export.meta.to_string(),
+            state: e.state.to_string(),
+            error: e.export.error.clone(),
+            created_at: e.export.created_at.format("%Y-%m-%d %H:%M").to_string(),
+            waited: e
+                .export
+                .processing_started_at
+                .map(|started| elapsed(e.export.created_at, started)),
+            took: e
+                .export
+                .processing_started_at
+                .zip(e.export.processed_at)
+                .map(|(started, processed)| elapsed(started, processed)),
+            deliveries: e
+                .deliveries
+                .into_iter()
+                .map(|d| DeliveryRow {
+                    state: match (&d.sent_at, &d.next_attempt_at, &d.error) {
+                        (Some(_), _, _) => "sent",
+                        (None, Some(_), _) => "retrying",
+                        (None, None, Some(_)) => "failed",
+                        (None, None, None) => "sending",
+                    }
+                    .to_owned(),
+                    volume: (e.artifacts.len() > 1).then_some(d.volume),
+                    destination: d.destination,
+                    error: d.error,
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Exports { exports })
+}
+
+fn human_size(bytes: i64) -> String {
+    match bytes {
+        0..=1023 => format!("{bytes} B"),
+        1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
+
+fn elapsed(from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+    let seconds = (to - from).num_seconds().max(0);
+
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
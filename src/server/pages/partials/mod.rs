@@ -1,4 +1,7 @@
 pub mod avatar;
 pub mod books;
 pub mod cover;
+pub mod errors;
+pub mod exports;
+pub mod subscriptions;
 pub mod token;
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use askama::Template;
+use axum::extract::State;
+use chrono::{DateTime, NaiveTime, Utc};
+use sqlx::PgPool;
+
+use crate::server::{auth::AuthKind, subscriptions::Subscription, Error};
+
+pub struct SubscriptionRow {
+    id: i32,
+    book_id: i32,
+    book_name: String,
+    schedule: String,
+    threshold: String,
+    quiet_start: String,
+    quiet_end: String,
+    next_run_at: String,
+    last_run_at: String,
+}
+
+pub struct BookOption {
+    id: i32,
+    name: String,
+}
+
+#[derive(Template)]
+#[template(path = "partials/subscriptions.html")]
+pub struct Subscriptions {
+    subscriptions: Vec<SubscriptionRow>,
+    books: Vec<BookOption>,
+}
+
+pub async fn subscriptions(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+) -> Result<Subscriptions, Error> {
+    let user = auth.human()?;
+
+    list_subscriptions(&pool, user.id).await
+}
+
+pub async fn list_subscriptions(pool: &PgPool, user_id: i32) -> Result<Subscriptions, Error> {
+    let books = sqlx::query_as!(BookOption, "SELECT id, name FROM books ORDER BY name ASC")
+        .fetch_all(pool)
+        .await?;
+
+    let subscriptions = sqlx::query_as!(
+        Subscription,
+        "SELECT * FROM subscriptions WHERE user_id = $1 ORDER BY id ASC",
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|subscription| SubscriptionRow {
+        id: subscription.id,
+        book_id: subscription.book_id,
+        book_name: books
+            .iter()
+            .find(|book| book.id == subscription.book_id)
+            .map(|book| book.name.clone())
+            .unwrap_or_default(),
+        schedule: subscription.schedule.unwrap_or_default(),
+        threshold: subscription
+            .threshold
+            .map(|threshold| threshold.to_string())
+            .unwrap_or_default(),
+        quiet_start: time_input(subscription.quiet_start),
+        quiet_end: time_input(subscription.quiet_end),
+        next_run_at: date(subscription.next_run_at),
+        last_run_at: date(subscription.last_run_at),
+    })
+    .collect();
+
+    Ok(Subscriptions {
+        subscriptions,
+        books,
+    })
+}
+
+fn time_input(time: Option<NaiveTime>) -> String {
+    time.map(|time| time.format("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn date(date: Option<DateTime<Utc>>) -> String {
+    date.map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or("-".to_owned())
+}
@@ -5,12 +5,14 @@ use crate::server::{auth::AuthKind, Error};
 
 #[derive(Template)]
 #[template(path = "settings.html")]
-pub struct SettingsTemplate {}
+pub struct SettingsTemplate {
+    custom_css: String,
+}
 
 pub async fn settings(auth: AuthKind) -> Result<SettingsTemplate, Error> {
-    if let Err(error) = auth.human() {
-        return Err(error);
-    }
+    let user = auth.human()?;
 
-    Ok(SettingsTemplate {})
+    Ok(SettingsTemplate {
+        custom_css: user.custom_css.clone().unwrap_or_default(),
+    })
 }
@@ -0,0 +1,28 @@
+pub mod update;
+
+use serde::{Deserialize, Serialize};
+
+// large enough for any hand-written stylesheet, small enough to sit in every export
+const MAX_CSS_SIZE: usize = 64 * 1024;
+
+/// Custom CSS as sent by the settings or book page.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CssForm {
+    #[serde(default)]
+    css: String,
+}
+
+impl CssForm {
+    /// The stylesheet to store, `None` once cleared.
+    pub fn css(self) -> Result<Option<String>, String> {
+        let css = self.css.trim();
+        if css.len() > MAX_CSS_SIZE {
+            return Err(format!(
+                "Stylesheets are limited to {} KB",
+                MAX_CSS_SIZE / 1024
+            ));
+        }
+
+        Ok((!css.is_empty()).then(|| css.to_owned()))
+    }
+}
@@ -0,0 +1,63 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use sqlx::PgPool;
+
+use crate::server::{auth::AuthKind, Error};
+
+use super::CssForm;
+
+pub async fn update_user_css(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Form(input): Form<CssForm>,
+) -> Result<Response, Error> {
+    let user = auth.human()?;
+    let css = match input.css() {
+        Ok(css) => css,
+        Err(message) => {
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(message)).into_response())
+        }
+    };
+
+    sqlx::query!(
+        "UPDATE users SET custom_css = $2 WHERE id = $1",
+        user.id,
+        css,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(Html("Stylesheet saved").into_response())
+}
+
+pub async fn update_book_css(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(book_id): Path<i32>,
+    Form(input): Form<CssForm>,
+) -> Result<Response, Error> {
+    auth.human()?;
+    let css = match input.css() {
+        Ok(css) => css,
+        Err(message) => {
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(message)).into_response())
+        }
+    };
+
+    let updated = sqlx::query!(
+        "UPDATE books SET custom_css = $2 WHERE id = $1",
+        book_id,
+        css,
+    )
+    .execute(&pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(Error::NotFound("Book not found".to_owned()));
+    }
+
+    Ok(Html("Stylesheet saved").into_response())
+}
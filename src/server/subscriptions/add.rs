@@ -0,0 +1,41 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use sqlx::PgPool;
+
+use crate::server::{auth::AuthKind, pages::partials::subscriptions::list_subscriptions, Error};
+
+use super::SubscriptionForm;
+
+pub async fn add_subscription(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Form(input): Form<SubscriptionForm>,
+) -> Result<Response, Error> {
+    let user = auth.human()?;
+    let rules = match input.rules() {
+        Ok(rules) => rules,
+        Err(message) => {
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(message)).into_response())
+        }
+    };
+
+    sqlx::query!(
+        "INSERT INTO subscriptions (user_id, book_id, schedule, threshold, quiet_start, quiet_end, next_run_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        user.id,
+        input.book_id,
+        rules.schedule,
+        rules.threshold,
+        rules.quiet_start,
+        rules.quiet_end,
+        rules.next_run_at,
+    )
+    .execute(&pool)
+    .await?;
+
+    Ok(list_subscriptions(&pool, user.id).await?.into_response())
+}
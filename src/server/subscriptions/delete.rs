@@ -0,0 +1,26 @@
+use axum::extract::{Path, State};
+use sqlx::PgPool;
+
+use crate::server::{
+    auth::AuthKind,
+    pages::partials::subscriptions::{list_subscriptions, Subscriptions},
+    Error,
+};
+
+pub async fn delete_subscription(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+) -> Result<Subscriptions, Error> {
+    let user = auth.human()?;
+
+    sqlx::query!(
+        "DELETE FROM subscriptions WHERE id = $1 AND user_id = $2",
+        id,
+        user.id,
+    )
+    .execute(&pool)
+    .await?;
+
+    list_subscriptions(&pool, user.id).await
+}
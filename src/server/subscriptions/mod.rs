@@ -0,0 +1,91 @@
+pub mod add;
+pub mod delete;
+pub mod update;
+
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveTime, Utc};
+use cron::Schedule;
+pub use models::subscription::Subscription;
+use serde::{Deserialize, Serialize};
+
+/// A subscription as sent by the settings page, empty inputs included.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SubscriptionForm {
+    book_id: i32,
+    #[serde(default)]
+    schedule: String,
+    #[serde(default)]
+    threshold: String,
+    #[serde(default)]
+    quiet_start: String,
+    #[serde(default)]
+    quiet_end: String,
+}
+
+/// The checked rules of a subscription.
+pub struct Rules {
+    schedule: Option<String>,
+    threshold: Option<i32>,
+    quiet_start: Option<NaiveTime>,
+    quiet_end: Option<NaiveTime>,
+    next_run_at: Option<DateTime<Utc>>,
+}
+
+impl SubscriptionForm {
+    pub fn rules(&self) -> Result<Rules, String> {
+        let schedule = Some(self.schedule.trim()).filter(|s| !s.is_empty());
+        let threshold = match self.threshold.trim() {
+            "" => None,
+            threshold => match threshold.parse::<i32>() {
+                Ok(threshold) if threshold > 0 => Some(threshold),
+                _ => return Err("The threshold must be a positive number".to_owned()),
+            },
+        };
+        if schedule.is_none() && threshold.is_none() {
+            return Err("A subscription needs a schedule, a threshold or both".to_owned());
+        }
+
+        let next_run_at = match schedule {
+            Some(schedule) => parse_schedule(schedule)?.upcoming(Utc).next(),
+            None => None,
+        };
+        let quiet_start = parse_time(&self.quiet_start)?;
+        let quiet_end = parse_time(&self.quiet_end)?;
+        if quiet_start.is_some() != quiet_end.is_some() {
+            return Err("Quiet hours need both a start and an end".to_owned());
+        }
+
+        Ok(Rules {
+            schedule: schedule.map(str::to_owned),
+            threshold,
+            quiet_start,
+            quiet_end,
+            next_run_at,
+        })
+    }
+}
+
+/// Parses a cron expression, in UTC.
+///
+/// The usual 5 fields (`0 8 * * Sun`) are accepted on top of the 6 or 7
+/// fields, seconds and years, of the `cron` crate.
+pub fn parse_schedule(expression: &str) -> Result<Schedule, String> {
+    let expression = match expression.split_whitespace().count() {
+        5 => format!("0 {expression}"),
+        _ => expression.to_owned(),
+    };
+
+    Schedule::from_str(&expression)
+        .map_err(|_| "The schedule must be a cron expression, such as 0 8 * * Sun".to_owned())
+}
+
+fn parse_time(time: &str) -> Result<Option<NaiveTime>, String> {
+    match time.trim() {
+        "" => Ok(None),
+        time => NaiveTime::parse_from_str(time, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+            .map(Some)
+            .map_err(|_| "Quiet hours must be times, such as 22:00".to_owned()),
+    }
+}
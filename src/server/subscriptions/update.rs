@@ -0,0 +1,51 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use sqlx::PgPool;
+
+use crate::server::{auth::AuthKind, pages::partials::subscriptions::list_subscriptions, Error};
+
+use super::SubscriptionForm;
+
+pub async fn update_subscription(
+    auth: AuthKind,
+    State(pool): State<PgPool>,
+    Path(id): Path<i32>,
+    Form(input): Form<SubscriptionForm>,
+) -> Result<Response, Error> {
+    let user = auth.human()?;
+    let rules = match input.rules() {
+        Ok(rules) => rules,
+        Err(message) => {
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(message)).into_response())
+        }
+    };
+
+    let updated = sqlx::query!(
+        "UPDATE subscriptions
+        SET schedule = $3,
+            threshold = $4,
+            quiet_start = $5,
+            quiet_end = $6,
+            next_run_at = $7
+        WHERE id = $1
+            AND user_id = $2",
+        id,
+        user.id,
+        rules.schedule,
+        rules.threshold,
+        rules.quiet_start,
+        rules.quiet_end,
+        rules.next_run_at,
+    )
+    .execute(&pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(Error::NotFound("subscription not found".to_owned()));
+    }
+
+    Ok(list_subscriptions(&pool, user.id).await?.into_response())
+}
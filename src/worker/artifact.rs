@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use sqlx::{postgres::types::PgInterval, PgPool};
+
+use super::delivery::Artifact;
+
+/// Volumes stored for an export, in order.
+pub async fn volumes(pool: &PgPool, export_id: i32) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT volume FROM artifacts
+        WHERE export_id = $1
+        ORDER BY volume ASC",
+        export_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Loads the ePub of one volume, volumes are big enough that they are only
+/// held in memory one at a time.
+pub async fn load(pool: &PgPool, export_id: i32, volume: i32) -> Result<Artifact, sqlx::Error> {
+    let artifact = sqlx::query!(
+        "SELECT title, description, filename, volume, content
+        FROM artifacts
+        WHERE export_id = $1 AND volume = $2",
+        export_id,
+        volume,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Artifact {
+        title: artifact.title,
+        description: artifact.description,
+        filename: artifact.filename,
+        volume: artifact.volume,
+        bytes: artifact.content.into(),
+    })
+}
+
+/// Keeps the ePub of a volume, so it can be downloaded or sent again without
+/// being generated again.
+pub async fn store(pool: &PgPool, export_id: i32, artifact: &Artifact) -> Result<(), sqlx::Error> {
+    let checksum = hex::encode(Sha256::digest(&artifact.bytes));
+
+    sqlx::query!(
+        "INSERT INTO artifacts (export_id, volume, filename, title, description, size, checksum, content)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (export_id, volume) DO NOTHING",
+        export_id,
+        artifact.volume,
+        artifact.filename,
+        artifact.title,
+        artifact.description,
+        artifact.bytes.len() as i64,
+        checksum,
+        artifact.bytes.as_ref(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes the volumes stored for an export.
+pub async fn delete(pool: &PgPool, export_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM artifacts WHERE export_id = $1", export_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes the artifacts older than `retention`.
+pub async fn purge(pool: &PgPool, retention: Duration) -> Result<(), sqlx::Error> {
+    let retention = PgInterval::try_from(retention).expect("retention should fit in an interval");
+
+    let purged = sqlx::query!(
+        "DELETE FROM artifacts
+        WHERE created_at < CURRENT_TIMESTAMP - $1::interval
+        RETURNING export_id",
+        retention,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !purged.is_empty() {
+        tracing::info!("Deleted the artifacts of {} old exports", purged.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+use axum::async_trait;
+
+use super::{Artifact, Delivery};
+use crate::worker::retry::Failure;
+
+/// Drops the ePub in a local directory, such as a Syncthing folder or a
+/// Calibre auto-add folder.
+pub struct Directory {
+    pub path: PathBuf,
+}
+
+#[async_trait]
+impl Delivery for Directory {
+    fn name(&self) -> &'static str {
+        "directory"
+    }
+
+    async fn deliver(&self, artifact: &Artifact) -> Result<(), Failure> {
+        let destination = self.path.join(&artifact.filename);
+        // written aside first so watchers never pick up a partial file
+        let partial = self.path.join(format!(".{}.part", artifact.filename));
+
+        tokio::fs::write(&partial, &artifact.bytes)
+            .await
+            .map_err(|e| Failure::retryable(format!("Cannot write {}: {e}", partial.display())))?;
+        tokio::fs::rename(&partial, &destination)
+            .await
+            .map_err(|e| Failure::retryable(format!("Cannot write {}: {e}", destination.display())))
+    }
+}
@@ -0,0 +1,112 @@
+use axum::async_trait;
+use reqwest::multipart;
+use serde::Serialize;
+
+use super::{Artifact, Delivery};
+use crate::worker::retry::Failure;
+
+#[derive(Debug, Serialize)]
+struct EmbedField {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Embed {
+    title: String,
+    #[serde(rename = "type")]
+    r#type: String,
+    description: String,
+    color: u32,
+    fields: Vec<EmbedField>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    content: String,
+    embeds: Vec<Embed>,
+}
+
+struct MessageBuilder {
+    content: String,
+    book_name: String,
+    description: String,
+}
+
+impl MessageBuilder {
+    fn new() -> Self {
+        Self {
+            content: "Your book is ready!".to_owned(),
+            book_name: "".to_owned(),
+            description: "".to_owned(),
+        }
+    }
+
+    fn book_name(mut self, book_name: String) -> Self {
+        self.book_name = book_name;
+        self
+    }
+
+    fn description(mut self, description: String) -> Self {
+        self.description = description;
+        self
+    }
+
+    fn build(self) -> Message {
+        Message {
+            content: self.content,
+            embeds: vec![Embed {
+                title: self.book_name,
+                r#type: "file".to_owned(),
+                description: self.description,
+                color: 0x91288a,
+                fields: vec![],
+            }],
+        }
+    }
+}
+
+/// Posts the ePub as an attachment of a Discord webhook message.
+pub struct Discord {
+    pub webhook_url: String,
+}
+
+#[async_trait]
+impl Delivery for Discord {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    async fn deliver(&self, artifact: &Artifact) -> Result<(), Failure> {
+        let message = MessageBuilder::new()
+            .book_name(artifact.title.clone())
+            .description(artifact.description.clone())
+            .build();
+
+        let file_part = multipart::Part::stream_with_length(
+            artifact.bytes.clone(),
+            artifact.bytes.len() as u64,
+        )
+        .file_name(artifact.filename.clone())
+        .mime_str("application/epub+zip")
+        .map_err(|e| Failure::permanent(e.to_string()))?;
+        let json_part = multipart::Part::text(
+            serde_json::to_string(&message).map_err(|e| Failure::permanent(e.to_string()))?,
+        );
+        let form = reqwest::multipart::Form::new()
+            .part("book.epub", file_part)
+            .part("payload_json", json_part);
+
+        let res = reqwest::Client::new()
+            .post(&self.webhook_url)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| Failure::retryable(format!("Discord error: {e}")))?;
+
+        match res.status().is_success() {
+            true => Ok(()),
+            false => Err(Failure::from_response("Discord", &res)),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+mod directory;
+mod discord;
+mod s3;
+mod smtp;
+
+use std::path::PathBuf;
+
+use axum::{async_trait, body::Bytes};
+use clap::Args;
+
+use self::{directory::Directory, discord::Discord, s3::S3Args, smtp::SmtpArgs};
+use super::retry::Failure;
+
+/// A generated ePub, ready to be delivered.
+pub struct Artifact {
+    pub title: String,
+    // what the export contains, for humans
+    pub description: String,
+    pub filename: String,
+    // from 1, most exports fit in a single volume
+    pub volume: i32,
+    // shared by the deliveries instead of copied for each one
+    pub bytes: Bytes,
+}
+
+/// A destination exports are sent to.
+///
+/// Every export goes to all the configured destinations, each one getting
+/// its own row in the `deliveries` table.
+#[async_trait]
+pub trait Delivery: Send + Sync {
+    /// Identifies the destination in the `deliveries` table.
+    fn name(&self) -> &'static str;
+
+    async fn deliver(&self, artifact: &Artifact) -> Result<(), Failure>;
+}
+
+#[derive(Debug, Args)]
+pub struct DeliveryArgs {
+    /// Directory exports are copied to, such as a Syncthing or Calibre auto-add folder
+    #[arg(long, env = "DELIVERY_DIR")]
+    delivery_dir: Option<PathBuf>,
+    #[command(flatten)]
+    smtp: SmtpArgs,
+    #[command(flatten)]
+    s3: S3Args,
+}
+
+impl DeliveryArgs {
+    pub fn destinations(
+        &self,
+        discord_webhook: Option<String>,
+    ) -> anyhow::Result<Vec<Box<dyn Delivery>>> {
+        let mut destinations: Vec<Box<dyn Delivery>> = vec![];
+
+        if let Some(webhook_url) = discord_webhook {
+            destinations.push(Box::new(Discord { webhook_url }));
+        }
+        if let Some(mailer) = self.smtp.mailer()? {
+            destinations.push(Box::new(mailer));
+        }
+        if let Some(path) = &self.delivery_dir {
+            if !path.is_dir() {
+                anyhow::bail!("{} is not a directory", path.display());
+            }
+            destinations.push(Box::new(Directory { path: path.clone() }));
+        }
+        if let Some(bucket) = self.s3.bucket()? {
+            destinations.push(Box::new(bucket));
+        }
+
+        Ok(destinations)
+    }
+}
@@ -0,0 +1,91 @@
+use axum::async_trait;
+use clap::Args;
+use s3::{creds::Credentials, error::S3Error, Bucket, Region};
+
+use super::{Artifact, Delivery};
+use crate::worker::retry::Failure;
+
+#[derive(Debug, Args)]
+pub struct S3Args {
+    /// Bucket receiving the exports, uploading is disabled when missing
+    #[arg(long, env = "S3_BUCKET")]
+    s3_bucket: Option<String>,
+    /// Endpoint of an S3-compatible storage such as MinIO, AWS when missing
+    #[arg(long, env = "S3_ENDPOINT")]
+    s3_endpoint: Option<String>,
+    #[arg(long, env = "S3_REGION", default_value = "us-east-1")]
+    s3_region: String,
+    #[arg(long, env = "S3_ACCESS_KEY")]
+    s3_access_key: Option<String>,
+    #[arg(long, env = "S3_SECRET_KEY")]
+    s3_secret_key: Option<String>,
+    /// Prepended to the name of every uploaded file
+    #[arg(long, env = "S3_PREFIX", default_value = "")]
+    s3_prefix: String,
+}
+
+/// Uploads the ePub to an S3-compatible bucket.
+pub struct S3 {
+    bucket: Box<Bucket>,
+    prefix: String,
+}
+
+impl S3Args {
+    /// Builds the uploader, or `None` when no bucket is configured.
+    pub fn bucket(&self) -> anyhow::Result<Option<S3>> {
+        let Some(name) = &self.s3_bucket else {
+            return Ok(None);
+        };
+
+        let credentials = Credentials::new(
+            self.s3_access_key.as_deref(),
+            self.s3_secret_key.as_deref(),
+            None,
+            None,
+            None,
+        )?;
+
+        let bucket = match &self.s3_endpoint {
+            // self hosted storages don't have a DNS entry per bucket
+            Some(endpoint) => Bucket::new(
+                name,
+                Region::Custom {
+                    region: self.s3_region.clone(),
+                    endpoint: endpoint.clone(),
+                },
+                credentials,
+            )?
+            .with_path_style(),
+            None => Bucket::new(name, self.s3_region.parse()?, credentials)?,
+        };
+
+        Ok(Some(S3 {
+            bucket,
+            prefix: self.s3_prefix.clone(),
+        }))
+    }
+}
+
+#[async_trait]
+impl Delivery for S3 {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    async fn deliver(&self, artifact: &Artifact) -> Result<(), Failure> {
+        self.bucket
+            .put_object_with_content_type(
+                format!("{}{}", self.prefix, artifact.filename),
+                &artifact.bytes,
+                "application/epub+zip",
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| match e {
+                S3Error::HttpFailWithBody(status, _) if status != 429 && status < 500 => {
+                    Failure::permanent(format!("S3 error: {e}"))
+                }
+                _ => Failure::retryable(format!("S3 error: {e}")),
+            })
+    }
+}
@@ -0,0 +1,164 @@
+use axum::async_trait;
+use base64::{engine::general_purpose, Engine};
+use clap::{Args, ValueEnum};
+use lettre::{
+    message::{
+        header::{ContentTransferEncoding, ContentType},
+        Attachment, Body, Mailbox, MultiPart, SinglePart,
+    },
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::{Artifact, Delivery};
+use crate::worker::retry::Failure;
+
+#[derive(Debug, Args)]
+pub struct SmtpArgs {
+    /// SMTP server used to mail exports, mailing is disabled when missing
+    #[arg(long, env = "SMTP_SERVER")]
+    smtp_server: Option<String>,
+    /// Defaults to 587 with STARTTLS, 465 with TLS and 25 without encryption
+    #[arg(long, env = "SMTP_PORT")]
+    smtp_port: Option<u16>,
+    #[arg(long, env = "SMTP_USER")]
+    smtp_user: Option<String>,
+    #[arg(long, env = "SMTP_PASSWORD")]
+    smtp_password: Option<String>,
+    /// Sender address, defaults to the SMTP user. It must be approved in the Kindle settings
+    #[arg(long, env = "SMTP_FROM")]
+    smtp_from: Option<String>,
+    /// How the connection is encrypted
+    #[arg(long, env = "SMTP_ENCRYPTION", value_enum, default_value_t = SmtpEncryption::Starttls)]
+    smtp_encryption: SmtpEncryption,
+    /// The `@kindle.com` address receiving the exports
+    #[arg(long, env = "SEND_TO")]
+    send_to: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SmtpEncryption {
+    /// Upgrades a plain connection, usually on port 587
+    Starttls,
+    /// Implicit TLS, usually on port 465
+    Tls,
+    /// Plaintext, credentials included, only meant for local sinks such as MailHog
+    None,
+}
+
+/// Mails the ePub as an attachment, usually to a Send-to-Kindle address.
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Mailbox,
+}
+
+impl SmtpArgs {
+    /// Builds the mailer, or `None` when no SMTP server is configured.
+    pub fn mailer(&self) -> anyhow::Result<Option<Mailer>> {
+        let Some(server) = &self.smtp_server else {
+            return Ok(None);
+        };
+
+        let to = self
+            .send_to
+            .as_ref()
+            .ok_or(anyhow::anyhow!("--send-to is required to mail exports"))?
+            .parse()?;
+        let from = self
+            .smtp_from
+            .as_ref()
+            .or(self.smtp_user.as_ref())
+            .ok_or(anyhow::anyhow!(
+                "--smtp-from or --smtp-user is required to mail exports"
+            ))?
+            .parse()?;
+
+        let (builder, port) = match self.smtp_encryption {
+            SmtpEncryption::Starttls => (
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(server)?,
+                587,
+            ),
+            SmtpEncryption::Tls => (AsyncSmtpTransport::<Tokio1Executor>::relay(server)?, 465),
+            SmtpEncryption::None => (
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(server),
+                25,
+            ),
+        };
+        let mut builder = builder.port(self.smtp_port.unwrap_or(port));
+
+        if let (Some(user), Some(password)) = (&self.smtp_user, &self.smtp_password) {
+            builder = builder.credentials(Credentials::new(user.clone(), password.clone()));
+        }
+
+        Ok(Some(Mailer {
+            transport: builder.build(),
+            from,
+            to,
+        }))
+    }
+}
+
+#[async_trait]
+impl Delivery for Mailer {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    async fn deliver(&self, artifact: &Artifact) -> Result<(), Failure> {
+        let attachment = Attachment::new(artifact.filename.clone()).body(
+            base64_body(&artifact.bytes),
+            ContentType::parse("application/epub+zip").expect("epub mime type should be valid"),
+        );
+
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(self.to.clone())
+            .subject(&artifact.title)
+            .multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain("Sent by wuxia2kindle".to_owned()))
+                    .singlepart(attachment),
+            )
+            .map_err(|e| Failure::permanent(e.to_string()))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map(|_| ())
+            .map_err(|e| match e.is_permanent() {
+                // 5xx replies, such as an unknown recipient or a rejected attachment
+                true => Failure::permanent(format!("SMTP error: {e}")),
+                false => Failure::retryable(format!("SMTP error: {e}")),
+            })
+    }
+}
+
+// lettre only encodes a body it owns, the ePub is encoded from the shared
+// bytes instead, in lines of 76 characters
+fn base64_body(bytes: &[u8]) -> Body {
+    let mut encoded = String::with_capacity(bytes.len() * 4 / 3 + bytes.len() / 57 * 2 + 4);
+    // 57 bytes make a full line
+    for (idx, line) in bytes.chunks(57).enumerate() {
+        if idx > 0 {
+            encoded.push_str("\r\n");
+        }
+        general_purpose::STANDARD.encode_string(line, &mut encoded);
+    }
+
+    Body::dangerous_pre_encoded(encoded.into_bytes(), ContentTransferEncoding::Base64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_is_encoded_like_lettre_does() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        let encoded = base64_body(&bytes);
+
+        assert_eq!(encoded.as_ref(), Body::new(bytes).as_ref());
+    }
+}
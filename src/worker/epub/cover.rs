@@ -0,0 +1,92 @@
+use std::io::Cursor;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose, Engine};
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, GenericImageView, ImageFormat,
+};
+
+// what Amazon recommends for Kindle covers, anything bigger is wasted
+const MAX_WIDTH: u32 = 1600;
+const MAX_HEIGHT: u32 = 2560;
+// above this, even a cover of the right dimensions is compressed again
+const MAX_SIZE: usize = 512 * 1024;
+const JPEG_QUALITY: u8 = 85;
+
+/// A cover ready to be added to an ePub.
+pub struct Cover {
+    pub filename: &'static str,
+    pub mime: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// Turns a `data:image/...;base64,` cover into an image every reader can
+/// show, downsized to Kindle dimensions.
+///
+/// The declared MIME type is only trusted once the image itself agrees with
+/// it. Grayscale covers are always JPEGs: e-ink screens show nothing else,
+/// and they are a third of the size.
+pub fn prepare(data_url: &str, grayscale: bool) -> Result<Cover> {
+    let (mime, data) = data_url
+        .strip_prefix("data:")
+        .and_then(|url| url.split_once(";base64,"))
+        .ok_or(anyhow!("cover is not a base64 data URL"))?;
+    let bytes = general_purpose::STANDARD
+        .decode(data.trim())
+        .context("cover is not valid base64")?;
+
+    let format = match mime {
+        "image/jpeg" | "image/jpg" => ImageFormat::Jpeg,
+        "image/png" => ImageFormat::Png,
+        "image/gif" => ImageFormat::Gif,
+        "image/webp" => ImageFormat::WebP,
+        _ => bail!("cover type {mime} is not supported, use JPEG, PNG, GIF or WebP"),
+    };
+    if image::guess_format(&bytes).ok() != Some(format) {
+        bail!("cover is declared as {mime} but is not one");
+    }
+    let image =
+        image::load_from_memory_with_format(&bytes, format).context("cover cannot be decoded")?;
+
+    let (width, height) = image.dimensions();
+    let oversized = width > MAX_WIDTH || height > MAX_HEIGHT;
+    // WebP is not supported by Kindles
+    let keep = !grayscale
+        && !oversized
+        && bytes.len() <= MAX_SIZE
+        && matches!(
+            format,
+            ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif
+        );
+    if keep {
+        return Ok(match format {
+            ImageFormat::Png => cover("cover.png", "image/png", bytes),
+            ImageFormat::Gif => cover("cover.gif", "image/gif", bytes),
+            _ => cover("cover.jpg", "image/jpeg", bytes),
+        });
+    }
+
+    let image = match oversized {
+        true => image.resize(MAX_WIDTH, MAX_HEIGHT, FilterType::Lanczos3),
+        false => image,
+    };
+    let image = match grayscale {
+        true => DynamicImage::ImageLuma8(image.to_luma8()),
+        // JPEGs have no transparency
+        false => DynamicImage::ImageRgb8(image.to_rgb8()),
+    };
+    let mut jpeg = Cursor::new(vec![]);
+    image
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY))
+        .context("cover cannot be compressed")?;
+
+    Ok(cover("cover.jpg", "image/jpeg", jpeg.into_inner()))
+}
+
+fn cover(filename: &'static str, mime: &'static str, bytes: Vec<u8>) -> Cover {
+    Cover {
+        filename,
+        mime,
+        bytes,
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    io::Cursor,
+    sync::{Arc, OnceLock},
+};
+
+use anyhow::{anyhow, Context, Result};
+use image::{codecs::jpeg::JpegEncoder, GrayImage};
+use models::epub::Epub;
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{fontdb::Database, Options, Tree},
+};
+
+use super::{cover::Cover, html::escape};
+
+const WIDTH: u32 = 1600;
+const HEIGHT: u32 = 2560;
+const MARGIN: u32 = 160;
+const FONT: &str = "DejaVu Serif";
+const MAX_TITLE_LINES: usize = 6;
+
+// shipped with the binary, workers don't have fonts installed
+static FONTS: OnceLock<Arc<Database>> = OnceLock::new();
+
+fn fonts() -> Arc<Database> {
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = Database::new();
+            fonts.load_font_data(include_bytes!("../../../assets/fonts/DejaVuSerif.ttf").to_vec());
+            fonts.load_font_data(
+                include_bytes!("../../../assets/fonts/DejaVuSerif-Bold.ttf").to_vec(),
+            );
+            Arc::new(fonts)
+        })
+        .clone()
+}
+
+/// Draws a cover for books without one, the same dark gradient with the
+/// title as the web UI, plus who wrote and translated it and the chapters
+/// the export holds.
+///
+/// It's gray anyway, so it's always a grayscale JPEG.
+pub fn generate(epub: &Epub) -> Result<Cover> {
+    let options = Options {
+        fontdb: fonts(),
+        font_family: FONT.to_owned(),
+        ..Default::default()
+    };
+    let tree = Tree::from_str(&svg(epub), &options).context("cannot lay out the cover")?;
+    let mut pixmap = Pixmap::new(WIDTH, HEIGHT).ok_or(anyhow!("cannot allocate the cover"))?;
+    resvg::render(&tree, Transform::default(), &mut pixmap.as_mut());
+
+    // the background is opaque, every channel holds the same gray
+    let gray = GrayImage::from_raw(
+        WIDTH,
+        HEIGHT,
+        pixmap.pixels().iter().map(|pixel| pixel.red()).collect(),
+    )
+    .ok_or(anyhow!("cannot convert the cover"))?;
+    let mut jpeg = Cursor::new(vec![]);
+    gray.write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, 85))
+        .context("cannot compress the cover")?;
+
+    Ok(Cover {
+        filename: "cover.jpg",
+        mime: "image/jpeg",
+        bytes: jpeg.into_inner(),
+    })
+}
+
+fn svg(epub: &Epub) -> String {
+    let center = WIDTH / 2;
+    let mut text = String::new();
+
+    // the longer the title, the smaller it gets, up to 6 lines
+    let (size, lines) = [160, 128, 104, 88, 72]
+        .into_iter()
+        .map(|size| (size, wrap(&epub.title, chars_per_line(size))))
+        .find(|(_, lines)| lines.len() <= MAX_TITLE_LINES)
+        .unwrap_or_else(|| (72, truncate(wrap(&epub.title, chars_per_line(72)))));
+    let line_height = size * 5 / 4;
+    let mut y = (HEIGHT * 2 / 5).saturating_sub(line_height * lines.len() as u32 / 2);
+    for line in lines {
+        text.push_str(&format!(
+            r#"<text x="{center}" y="{y}" font-size="{size}" font-weight="bold">{}</text>"#,
+            escape(&line)
+        ));
+        y += line_height;
+    }
+
+    y += 120;
+    let credits = [
+        epub.author.as_ref().map(|author| format!("by {author}")),
+        epub.translator
+            .as_ref()
+            .map(|translator| format!("translated by {translator}")),
+    ];
+    for credit in credits.into_iter().flatten() {
+        for line in wrap(&credit, chars_per_line(64)) {
+            text.push_str(&format!(
+                r#"<text x="{center}" y="{y}" font-size="64">{}</text>"#,
+                escape(&line)
+            ));
+            y += 80;
+        }
+    }
+
+    if let Some((from, to)) = epub.chapter_range {
+        let chapters = match from == to {
+            true => format!("Chapter {from}"),
+            false => format!("Chapters {from}–{to}"),
+        };
+        text.push_str(&format!(
+            r#"<text x="{center}" y="{}" font-size="72">{chapters}</text>"#,
+            HEIGHT - MARGIN - 40
+        ));
+    }
+
+    // same gradient as the `cover.html` partial
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}">
+            <defs>
+                <linearGradient id="background" x1="0" y1="0" x2="0" y2="1">
+                    <stop offset="0.01" stop-color="rgb(200,200,200)"/>
+                    <stop offset="0.5" stop-color="rgb(100,100,100)"/>
+                    <stop offset="0.99" stop-color="rgb(18,18,18)"/>
+                </linearGradient>
+            </defs>
+            <rect width="100%" height="100%" fill="url(#background)"/>
+            <g fill="rgb(240,240,240)" font-family="{FONT}" text-anchor="middle">{text}</g>
+        </svg>"#
+    )
+}
+
+// titles still too long at the smallest size are cut short
+fn truncate(mut lines: Vec<String>) -> Vec<String> {
+    if lines.len() > MAX_TITLE_LINES {
+        lines.truncate(MAX_TITLE_LINES);
+        lines[MAX_TITLE_LINES - 1].push('…');
+    }
+    lines
+}
+
+// DejaVu Serif glyphs are a bit over half as wide as they are high
+fn chars_per_line(font_size: u32) -> usize {
+    ((WIDTH - 2 * MARGIN) * 10 / (font_size * 6)) as usize
+}
+
+/// Breaks `text` in lines of at most `width` characters, cutting words
+/// longer than a line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word = word.chars().collect::<Vec<char>>();
+        while word.len() > width {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(word.drain(..width).collect());
+        }
+        let word = word.into_iter().collect::<String>();
+        if word.is_empty() {
+            continue;
+        }
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
@@ -0,0 +1,203 @@
+use models::chapter::Footnote;
+
+use crate::server::chapters::content::{sanitize, sanitize_template};
+
+/// Wraps a page body in an XHTML document.
+///
+/// `body` has to be well-formed already, see [`xhtml`] for chapters.
+pub fn wrap_html(title: &str, language: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version='1.0' encoding='utf-8'?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="{language}" xml:lang="{language}">
+    <head>
+        <title>{}</title>
+        <meta http-equiv="Content-Type" content="text/html; charset=utf-8"/>
+        <link rel="stylesheet" type="text/css" href="stylesheet.css"/>
+    </head>
+    <body class="calibre">{body}</body></html>"#,
+        escape(title),
+        language = escape_attribute(language),
+    )
+}
+
+/// Turns chapter HTML into XHTML.
+///
+/// The HTML is sanitized again, older chapters predate sanitizing, which
+/// also gives the way html5ever writes HTML back: lowercase names, quoted
+/// attributes and escaped text. Only void elements and `&nbsp;`, unknown to
+/// XML, are left to fix.
+///
+/// Links to footnotes become EPUB noterefs, see [`footnotes`].
+pub fn xhtml(html: &str) -> String {
+    to_xhtml(sanitize(html))
+}
+
+/// Turns the markup of a book's template, once rendered, into XHTML the same
+/// way as chapters.
+pub fn template_xhtml(html: &str) -> String {
+    to_xhtml(sanitize_template(html))
+}
+
+fn to_xhtml(html: String) -> String {
+    let html = html.replace("&nbsp;", "&#160;");
+    let mut xhtml = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+
+    while let Some(start) = rest.find('<') {
+        xhtml.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = tag_end(rest);
+        let tag = &rest[..end];
+        let name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or_default();
+        match (name, footnote_number(tag)) {
+            ("a", Some(number)) => xhtml.push_str(&format!(
+                r##"<a epub:type="noteref" href="#footnote-{number}" id="noteref-{number}" class="noteref">"##
+            )),
+            _ if VOID_ELEMENTS.contains(&name) && !tag.ends_with("/>") => {
+                xhtml.push_str(&tag[..tag.len() - 1]);
+                xhtml.push_str("/>");
+            }
+            _ => xhtml.push_str(tag),
+        }
+        rest = &rest[end..];
+    }
+    xhtml.push_str(rest);
+
+    xhtml
+}
+
+/// Notes section ending a chapter, its footnotes pop up when their noteref
+/// is followed on readers supporting it, like Kindle, and are read at the end
+/// of the chapter on the others.
+pub fn footnotes(footnotes: &[Footnote]) -> String {
+    if footnotes.is_empty() {
+        return String::new();
+    }
+
+    let notes: String = footnotes
+        .iter()
+        .enumerate()
+        .map(|(idx, footnote)| {
+            let number = idx + 1;
+            format!(
+                r##"<aside epub:type="footnote" id="footnote-{number}" class="footnote-body"><p class="footnote-marker"><a href="#noteref-{number}">{}</a></p>{}</aside>"##,
+                escape(&footnote.marker),
+                xhtml(&footnote.content),
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<section epub:type="endnotes" class="footnotes"><hr/><h3>Notes</h3>{notes}</section>"#
+    )
+}
+
+// number of the footnote a link tag points to
+fn footnote_number(tag: &str) -> Option<usize> {
+    let (_, href) = tag.split_once(r##"href="#footnote-"##)?;
+    let (number, _) = href.split_once('"')?;
+
+    number.parse().ok()
+}
+
+// the ones the sanitizer lets through
+const VOID_ELEMENTS: [&str; 3] = ["br", "hr", "img"];
+
+// index right after the `>` closing the tag `html` starts with, attribute
+// values can hold a `>`
+pub fn tag_end(html: &str) -> usize {
+    let mut quoted = false;
+    for (index, c) in html.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '>' if !quoted => return index + 1,
+            _ => {}
+        }
+    }
+
+    html.len()
+}
+
+/// Escapes text to put it in XML, between tags.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escapes text to put it in a double quoted XML attribute.
+pub fn escape_attribute(text: &str) -> String {
+    escape(text).replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_stays_escaped() {
+        assert_eq!(
+            xhtml("<p>Fish &amp; chips, 1 &lt; 2</p>"),
+            "<p>Fish &amp; chips, 1 &lt; 2</p>"
+        );
+    }
+
+    #[test]
+    fn bare_ampersands_and_brackets_are_escaped() {
+        assert_eq!(
+            xhtml("<p>Fish & chips, 1 < 2 > 0</p>"),
+            "<p>Fish &amp; chips, 1 &lt; 2 &gt; 0</p>"
+        );
+    }
+
+    #[test]
+    fn unclosed_tags_are_closed() {
+        assert_eq!(xhtml("<p>One<p><em>Two"), "<p>One</p><p><em>Two</em></p>");
+    }
+
+    #[test]
+    fn void_elements_are_self_closed() {
+        assert_eq!(xhtml("<p>One<br>Two</p><hr>"), "<p>One<br/>Two</p><hr/>");
+    }
+
+    #[test]
+    fn non_breaking_spaces_are_numeric() {
+        assert_eq!(xhtml("<p>A&nbsp;B</p>"), "<p>A&#160;B</p>");
+    }
+
+    #[test]
+    fn empty_paragraphs_are_kept() {
+        assert_eq!(xhtml("<p></p><p>Text</p>"), "<p></p><p>Text</p>");
+    }
+
+    #[test]
+    fn footnote_links_become_noterefs() {
+        assert_eq!(
+            xhtml(r##"<p>Word<a href="#footnote-2">2</a></p>"##),
+            r##"<p>Word<a epub:type="noteref" href="#footnote-2" id="noteref-2" class="noteref">2</a></p>"##
+        );
+    }
+
+    #[test]
+    fn quoted_brackets_do_not_end_tags() {
+        assert_eq!(tag_end(r#"<a title="a > b">text"#), 17);
+    }
+
+    #[test]
+    fn footnotes_section() {
+        let footnotes = [Footnote {
+            marker: "<1>".to_owned(),
+            content: "A note".to_owned(),
+        }];
+
+        assert_eq!(
+            super::footnotes(&footnotes),
+            r##"<section epub:type="endnotes" class="footnotes"><hr/><h3>Notes</h3><aside epub:type="footnote" id="footnote-1" class="footnote-body"><p class="footnote-marker"><a href="#noteref-1">&lt;1&gt;</a></p>A note</aside></section>"##
+        );
+        assert_eq!(super::footnotes(&[]), "");
+    }
+}
@@ -0,0 +1,180 @@
+use std::io::{self, Cursor, Read, Seek, Write};
+
+use epub_builder::{EpubBuilder, MetadataOpf, Result, ZipLibrary};
+use models::epub::Epub;
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use super::html::escape;
+
+/// Writes everything about the book itself in `content.opf`, besides its
+/// title.
+pub fn add_metadata(builder: &mut EpubBuilder<ZipLibrary>, epub: &Epub) -> Result<()> {
+    builder.set_uuid(epub.identifier);
+    builder.metadata("lang", &epub.language)?;
+
+    if let Some(author) = &epub.author {
+        builder.metadata("author", author)?;
+    }
+    // the translator is added by `fix_package`
+    if let Some(description) = &epub.description {
+        builder.metadata("description", description)?;
+    }
+    for subject in &epub.subjects {
+        builder.metadata("subject", subject)?;
+    }
+    if let Some(series) = &epub.series {
+        // read by Calibre, which sends it along to Kindles
+        builder
+            .add_metadata_opf(Box::new(MetadataOpf {
+                name: "calibre:series".to_owned(),
+                content: series.name.clone(),
+            }))
+            .add_metadata_opf(Box::new(MetadataOpf {
+                name: "calibre:series_index".to_owned(),
+                content: series.index.to_string(),
+            }));
+    }
+
+    Ok(())
+}
+
+const PACKAGE: &str = "OEBPS/content.opf";
+
+/// Hands the archive the builder writes straight to `fix_package`, without
+/// keeping a copy of it.
+///
+/// The builder holds the whole archive and writes it in one go, a second
+/// write fails instead of leaving a broken ePub.
+pub struct FixPackage<'a, W: Write + Seek> {
+    epub: &'a Epub,
+    writer: Option<W>,
+}
+
+impl<'a, W: Write + Seek> FixPackage<'a, W> {
+    pub fn new(epub: &'a Epub, writer: W) -> Self {
+        FixPackage {
+            epub,
+            writer: Some(writer),
+        }
+    }
+}
+
+impl<W: Write + Seek> Write for FixPackage<'_, W> {
+    fn write(&mut self, archive: &[u8]) -> io::Result<usize> {
+        let writer = self
+            .writer
+            .take()
+            .ok_or_else(|| io::Error::other("the archive was already written"))?;
+        fix_package(archive, self.epub, writer).map_err(io::Error::other)?;
+
+        Ok(archive.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Copies the `archive` written by the builder to `writer`, with what its
+/// `content.opf` can't hold fixed.
+///
+/// The builder numbers languages and authors the same way, so both end up
+/// with the `epub-creator-0` id, and it can't write `dc:contributor`
+/// elements. The other files are copied without being decompressed.
+fn fix_package<W: Write + Seek>(archive: &[u8], epub: &Epub, writer: W) -> Result<()> {
+    let mut archive = ZipArchive::new(Cursor::new(archive))?;
+    let mut fixed = ZipWriter::new(writer);
+
+    for idx in 0..archive.len() {
+        if archive.name_for_index(idx) != Some(PACKAGE) {
+            fixed.raw_copy_file(archive.by_index_raw(idx)?)?;
+            continue;
+        }
+
+        let mut opf = String::new();
+        archive.by_index(idx)?.read_to_string(&mut opf)?;
+        fixed.start_file(PACKAGE, SimpleFileOptions::default())?;
+        fixed.write_all(package(&opf, epub).as_bytes())?;
+    }
+    fixed.finish()?;
+
+    Ok(())
+}
+
+fn package(opf: &str, epub: &Epub) -> String {
+    let mut opf = opf.replace(
+        r#"<dc:language id="epub-creator-"#,
+        r#"<dc:language id="epub-language-"#,
+    );
+
+    if let Some(translator) = &epub.translator {
+        let contributor = format!(
+            r##"  <dc:contributor id="translator">{}</dc:contributor>
+    <meta refines="#translator" property="role" scheme="marc:relators">trl</meta>
+  </metadata>"##,
+            escape(translator)
+        );
+        opf = opf.replacen("</metadata>", &contributor, 1);
+    }
+
+    opf
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use super::*;
+
+    const OPF: &str = r#"  <metadata>
+    <dc:language id="epub-creator-0">en</dc:language>
+    <dc:creator id="epub-creator-0">Author</dc:creator>
+  </metadata>"#;
+
+    fn epub(translator: Option<&str>) -> Epub {
+        Epub {
+            title: "Book".to_owned(),
+            author: Some("Author".to_owned()),
+            translator: translator.map(str::to_owned),
+            cover: None,
+            grayscale_cover: false,
+            style: Default::default(),
+            toc_position: Default::default(),
+            numbered_toc: false,
+            custom_css: None,
+            identifier: Uuid::nil(),
+            language: "en".to_owned(),
+            description: None,
+            subjects: vec![],
+            series: None,
+            chapter_range: None,
+            exported_on: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+            templates: Default::default(),
+        }
+    }
+
+    #[test]
+    fn languages_get_their_own_ids() {
+        assert_eq!(
+            package(OPF, &epub(None)),
+            r#"  <metadata>
+    <dc:language id="epub-language-0">en</dc:language>
+    <dc:creator id="epub-creator-0">Author</dc:creator>
+  </metadata>"#
+        );
+    }
+
+    #[test]
+    fn translator_is_a_contributor() {
+        assert_eq!(
+            package(OPF, &epub(Some("Tom & Jerry"))),
+            r##"  <metadata>
+    <dc:language id="epub-language-0">en</dc:language>
+    <dc:creator id="epub-creator-0">Author</dc:creator>
+    <dc:contributor id="translator">Tom &amp; Jerry</dc:contributor>
+    <meta refines="#translator" property="role" scheme="marc:relators">trl</meta>
+  </metadata>"##
+        );
+    }
+}
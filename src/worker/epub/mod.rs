@@ -0,0 +1,266 @@
+mod cover;
+mod generated_cover;
+pub mod html;
+mod metadata;
+mod styles;
+pub mod templates;
+
+use std::{
+    fmt::Display,
+    io::{Cursor, Seek, Write},
+};
+
+use askama::Template;
+use epub_builder::{EpubBuilder, EpubContent, EpubVersion, ReferenceType, ZipLibrary};
+pub use models::epub::Epub;
+use models::epub::{EpubChapter, EpubImage, TocEntry, TocPosition};
+
+use self::{
+    html::{footnotes, wrap_html, xhtml},
+    metadata::{add_metadata, FixPackage},
+    styles::stylesheet,
+    templates::{
+        ChapterContext, ChapterFooter, ChapterHeader, Colophon, EpubPage, TitlePage, TocPage,
+    },
+};
+use super::retry::Failure;
+
+pub struct MyEpub(pub Epub);
+
+const TOC_NAME: &str = "Table of Contents";
+
+/// What the ePub is made of besides its metadata, added as it arrives so a
+/// book never has to be held in memory whole.
+pub enum Part {
+    Chapter(EpubChapter),
+    // added before the chapters using it
+    Image(EpubImage),
+}
+
+/// Why an ePub could not be generated.
+#[derive(Debug)]
+pub enum GenerateError {
+    /// The book cover can't be used, or one can't be generated.
+    Cover(anyhow::Error),
+    /// Content or metadata rejected by the builder.
+    Content(epub_builder::Error),
+    /// A page could not be rendered from its template.
+    Template(askama::Error),
+    /// The archive could not be written.
+    Zip(epub_builder::Error),
+}
+
+impl Display for GenerateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cover(e) => write!(f, "Invalid cover: {e:#}"),
+            Self::Content(e) => write!(f, "Invalid content: {e}"),
+            Self::Template(e) => write!(f, "Cannot render a page: {e}"),
+            Self::Zip(e) => write!(f, "Cannot write the ePub: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+impl From<GenerateError> for Failure {
+    fn from(e: GenerateError) -> Self {
+        match e {
+            GenerateError::Zip(_) => Self::retryable(e.to_string()),
+            _ => Self::permanent(e.to_string()),
+        }
+    }
+}
+
+impl MyEpub {
+    /// Generates the ePub in memory.
+    pub fn to_bytes(
+        &self,
+        contents: &[TocEntry],
+        parts: impl IntoIterator<Item = Part>,
+    ) -> Result<Vec<u8>, GenerateError> {
+        let mut bytes = Cursor::new(vec![]);
+        self.generate(contents, parts, &mut bytes)?;
+
+        Ok(bytes.into_inner())
+    }
+
+    /// Writes the ePub to `writer`.
+    ///
+    /// Parts are compressed as soon as they are added, only the archive is
+    /// kept until it's written. `contents` lists the chapters `parts` holds,
+    /// in the same order, so the table of contents can come before them.
+    ///
+    /// Besides the `nav.xhtml` and `toc.ncx` menus, for older Kindles, the
+    /// landmarks and guide point to the cover, the table of contents and the
+    /// first chapter, where readers start.
+    pub fn generate<W: Write + Seek>(
+        &self,
+        contents: &[TocEntry],
+        parts: impl IntoIterator<Item = Part>,
+        writer: W,
+    ) -> Result<(), GenerateError> {
+        let epub = &self.0;
+        let mut builder = ZipLibrary::new()
+            .and_then(EpubBuilder::new)
+            .map_err(GenerateError::Zip)?;
+
+        builder
+            .epub_version(EpubVersion::V30)
+            .metadata("title", &epub.title)
+            .map_err(GenerateError::Content)?
+            .metadata("toc_name", TOC_NAME)
+            .map_err(GenerateError::Content)?
+            .stylesheet(stylesheet(epub.style, epub.custom_css.as_deref()).as_bytes())
+            .map_err(GenerateError::Content)?;
+
+        add_metadata(&mut builder, epub).map_err(GenerateError::Content)?;
+        let cover = match &epub.cover {
+            Some(cover) => cover::prepare(cover, epub.grayscale_cover),
+            None => generated_cover::generate(epub),
+        }
+        .map_err(GenerateError::Cover)?;
+        builder
+            // book cover for file system
+            .add_cover_image(cover.filename, cover.bytes.as_slice(), cover.mime)
+            .map_err(GenerateError::Content)?
+            // actual cover when opening the epub
+            .add_content(
+                EpubContent::new(
+                    "cover.xhtml",
+                    wrap_html(
+                        "Cover",
+                        &epub.language,
+                        &format!(r#"<img src="{}" alt="Cover" />"#, cover.filename),
+                    )
+                    .as_bytes(),
+                )
+                .title("Cover")
+                .reftype(ReferenceType::Cover),
+            )
+            .map_err(GenerateError::Content)?;
+
+        let title_page = TitlePage::new(epub)
+            .render_with(epub.templates.title_page.as_deref())
+            .map_err(GenerateError::Template)?;
+        builder
+            .add_content(
+                EpubContent::new(
+                    "title.xhtml",
+                    wrap_html(&epub.title, &epub.language, &title_page).as_bytes(),
+                )
+                .title(&epub.title)
+                .reftype(ReferenceType::TitlePage),
+            )
+            .map_err(GenerateError::Content)?;
+        if epub.toc_position == TocPosition::Front {
+            self.add_toc(&mut builder, contents)?;
+        }
+
+        let mut chapter_idx = 0;
+        for part in parts {
+            match part {
+                Part::Image(image) => {
+                    builder
+                        .add_resource(&image.filename, image.bytes.as_slice(), image.mime)
+                        .map_err(GenerateError::Content)?;
+                }
+                Part::Chapter(chapter) => {
+                    chapter_idx += 1;
+                    let context = ChapterContext::new(&chapter);
+                    let header = ChapterHeader { chapter: &context }
+                        .render_with(epub.templates.chapter_header.as_deref())
+                        .map_err(GenerateError::Template)?;
+                    let footer = ChapterFooter { chapter: &context }
+                        .render_with(epub.templates.chapter_footer.as_deref())
+                        .map_err(GenerateError::Template)?;
+                    let body = format!(
+                        "{header}{}{}{footer}",
+                        xhtml(&chapter.content),
+                        footnotes(&chapter.footnotes),
+                    );
+                    let page = wrap_html(&chapter.title, &epub.language, &body);
+
+                    let mut content =
+                        EpubContent::new(format!("chapter_{chapter_idx}.xhtml"), page.as_bytes())
+                            .title(self.toc_label(chapter.number, &chapter.title));
+                    // the start location, the other chapters are only listed
+                    // in the table of contents
+                    if chapter_idx == 1 {
+                        content = content.reftype(ReferenceType::Text);
+                    }
+                    builder
+                        .add_content(content)
+                        .map_err(GenerateError::Content)?;
+                }
+            }
+        }
+
+        if epub.toc_position == TocPosition::Back {
+            self.add_toc(&mut builder, contents)?;
+        }
+
+        let colophon = Colophon::new(epub, chapter_idx)
+            .render_with(epub.templates.colophon.as_deref())
+            .map_err(GenerateError::Template)?;
+        builder
+            .add_content(
+                EpubContent::new(
+                    "colophon.xhtml",
+                    wrap_html("About this export", &epub.language, &colophon).as_bytes(),
+                )
+                .title("About this export")
+                .reftype(ReferenceType::Colophon),
+            )
+            .map_err(GenerateError::Content)?;
+
+        builder
+            .generate(FixPackage::new(epub, writer))
+            .map_err(GenerateError::Zip)
+    }
+
+    // table of contents page, where the builder's own inline one would also
+    // hold the landmarks
+    fn add_toc(
+        &self,
+        builder: &mut EpubBuilder<ZipLibrary>,
+        contents: &[TocEntry],
+    ) -> Result<(), GenerateError> {
+        let entries = contents
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                (
+                    format!("chapter_{}.xhtml", idx + 1),
+                    self.toc_label(entry.number, &entry.title),
+                )
+            })
+            .collect();
+        let toc = TocPage {
+            title: TOC_NAME,
+            entries,
+        }
+        .render()
+        .map_err(GenerateError::Template)?;
+
+        builder
+            .add_content(
+                EpubContent::new(
+                    "toc.xhtml",
+                    wrap_html(TOC_NAME, &self.0.language, &toc).as_bytes(),
+                )
+                .title(TOC_NAME)
+                .reftype(ReferenceType::Toc),
+            )
+            .map_err(GenerateError::Content)?;
+
+        Ok(())
+    }
+
+    fn toc_label(&self, number: i32, title: &str) -> String {
+        match self.0.numbered_toc {
+            true => format!("{number}. {title}"),
+            false => title.to_owned(),
+        }
+    }
+}
@@ -1,12 +1,218 @@
-pub fn custom_styles() -> &'static str {
+use models::epub::StyleProfile;
+
+/// Stylesheet passed to the ePub builder: the profile's base, our own
+/// tweaks, then the user and book CSS so they win over both.
+pub fn stylesheet(profile: StyleProfile, custom_css: Option<&str>) -> String {
+    let base = match profile {
+        StyleProfile::Kindle => kindle().to_owned(),
+        StyleProfile::Kobo => boilerplate().to_owned(),
+        StyleProfile::EInk => format!("{}\n{}", boilerplate(), e_ink()),
+        StyleProfile::LargePrint => format!("{}\n{}", kindle(), large_print()),
+    };
+
+    [
+        base.as_str(),
+        custom_styles(),
+        custom_css.unwrap_or_default(),
+    ]
+    .join("\n")
+}
+
+fn custom_styles() -> &'static str {
     r###"
-        #toc + nav {
-            display: none;
+        .title-page,
+        .colophon {
+            text-align: center;
+        }
+
+        .title-page p,
+        .colophon p,
+        .chapter-source {
+            text-indent: 0;
+        }
+
+        .toc ol {
+            list-style-type: none;
+            padding-left: 0;
+        }
+
+        .toc li {
+            margin: 0.3em 0;
+        }
+
+        .chapter-source {
+            margin-top: 2em;
+            font-size: 0.8em;
+            text-align: right;
+        }
+
+        .footnotes {
+            font-size: 0.9em;
+        }
+
+        /* not `.footnote`, which the profiles make superscript */
+        .footnote-body {
+            margin: 0.5em 0;
+            vertical-align: baseline;
+            font-size: 1em;
+        }
+
+        .footnote-marker {
+            text-indent: 0;
+            font-weight: bold;
         }
+
+        .noteref {
+            vertical-align: super;
+            font-size: 0.75em;
+            line-height: 0;
+        }
+
+        sup > .noteref {
+            vertical-align: baseline;
+            font-size: 1em;
+        }
+    "###
+}
+
+/// Sticks to what KF8 renders: no resets, `@page`, `hyphens` or pixel
+/// margins, which Kindles either ignore or get wrong.
+fn kindle() -> &'static str {
+    r###"
+    body {
+      text-align: justify;
+    }
+
+    h1,
+    h2 {
+      text-indent: 0;
+      text-align: center;
+      font-weight: bold;
+      page-break-before: always;
+      page-break-after: avoid;
+    }
+
+    h1 {
+      margin: 3em 0 0 0;
+      font-size: 2em;
+    }
+
+    h2 {
+      margin: 1.5em 0 1em 0;
+      font-size: 1.5em;
+    }
+
+    h3,
+    h4,
+    h5,
+    h6 {
+      text-indent: 0;
+      text-align: left;
+      font-weight: bold;
+      page-break-after: avoid;
+    }
+
+    p {
+      text-indent: 1.25em;
+      margin: 0;
+    }
+
+    h1 + p,
+    h2 + p,
+    hr + p {
+      text-indent: 0;
+    }
+
+    ul,
+    ol {
+      margin: 1em 0 0 2em;
+      text-align: left;
+    }
+
+    blockquote {
+      margin: 1em 1.5em 0 1.5em;
+    }
+
+    hr {
+      margin: 1em 25%;
+      width: 50%;
+    }
+
+    img {
+      max-width: 100%;
+    }
+
+    table {
+      margin: 1em auto;
+    }
+
+    th,
+    td {
+      padding: 0.2em;
+      border: 1px solid black;
+    }
+
+    .footnote {
+      vertical-align: super;
+      font-size: 0.75em;
+      text-decoration: none;
+    }
+    "###
+}
+
+/// Greys and colours turn into washed out dithering on e-ink screens.
+fn e_ink() -> &'static str {
+    r###"
+    body,
+    a {
+      color: black;
+      background-color: transparent;
+    }
+
+    a {
+      text-decoration: underline;
+    }
+
+    hr,
+    div.pullquote hr {
+      color: black;
+      background-color: black;
+      border: 0;
+      height: 2px;
+    }
+
+    img {
+      page-break-inside: avoid;
+    }
+    "###
+}
+
+/// Bigger, ragged right text: justification leaves wide gaps between words
+/// once only a few of them fit on a line.
+fn large_print() -> &'static str {
+    r###"
+    body {
+      text-align: left;
+      font-size: 1.4em;
+      line-height: 1.6;
+    }
+
+    p {
+      text-indent: 0;
+      margin: 0 0 0.8em 0;
+    }
+
+    h1 {
+      font-size: 1.6em;
+    }
+
+    h2 {
+      font-size: 1.3em;
+    }
     "###
 }
 
-pub fn stylesheet() -> &'static str {
+fn boilerplate() -> &'static str {
     r###"
     /* BB eBooks BoilerPlate EPUB */
     /* Modify as Needed */
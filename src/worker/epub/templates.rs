@@ -0,0 +1,232 @@
+use std::ops::Range;
+
+use askama::Template;
+use models::epub::{Epub, EpubChapter};
+
+use super::html::{escape_attribute, template_xhtml};
+
+/// A page written from a template, the askama one unless the book has its
+/// own.
+///
+/// A book's templates are plain markup, `{{ name }}` being replaced by the
+/// variable of that name and unknown ones by nothing. They are sanitized once
+/// rendered.
+pub trait EpubPage: Template {
+    /// Names of the variables the book's templates can use.
+    const VARIABLES: &'static [&'static str];
+
+    /// Values of the variables, in the same order.
+    fn values(&self) -> Vec<String>;
+
+    fn render_with(&self, custom: Option<&str>) -> askama::Result<String> {
+        match custom {
+            Some(custom) => Ok(template_xhtml(&substitute(
+                custom,
+                Self::VARIABLES,
+                &self.values(),
+            ))),
+            None => self.render(),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "epub/title.html")]
+pub struct TitlePage<'a> {
+    title: &'a str,
+    author: Option<&'a str>,
+    translator: Option<&'a str>,
+    range: Option<String>,
+    exported_on: String,
+}
+
+impl<'a> TitlePage<'a> {
+    pub fn new(epub: &'a Epub) -> Self {
+        TitlePage {
+            title: &epub.title,
+            author: epub.author.as_deref(),
+            translator: epub.translator.as_deref(),
+            range: range(epub),
+            exported_on: exported_on(epub),
+        }
+    }
+}
+
+impl EpubPage for TitlePage<'_> {
+    const VARIABLES: &'static [&'static str] =
+        &["title", "author", "translator", "range", "exported_on"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.title.to_owned(),
+            self.author.unwrap_or_default().to_owned(),
+            self.translator.unwrap_or_default().to_owned(),
+            self.range.clone().unwrap_or_default(),
+            self.exported_on.clone(),
+        ]
+    }
+}
+
+/// What the header and footer of a chapter know about it.
+pub struct ChapterContext<'a> {
+    title: &'a str,
+    number: i32,
+    source: Option<&'a str>,
+}
+
+impl<'a> ChapterContext<'a> {
+    const VARIABLES: &'static [&'static str] = &["title", "number", "source"];
+
+    pub fn new(chapter: &'a EpubChapter) -> Self {
+        ChapterContext {
+            title: &chapter.title,
+            number: chapter.number,
+            source: chapter.url.as_deref(),
+        }
+    }
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.title.to_owned(),
+            self.number.to_string(),
+            self.source.unwrap_or_default().to_owned(),
+        ]
+    }
+}
+
+#[derive(Template)]
+#[template(path = "epub/chapter_header.html")]
+pub struct ChapterHeader<'a> {
+    pub chapter: &'a ChapterContext<'a>,
+}
+
+impl EpubPage for ChapterHeader<'_> {
+    const VARIABLES: &'static [&'static str] = ChapterContext::VARIABLES;
+
+    fn values(&self) -> Vec<String> {
+        self.chapter.values()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "epub/chapter_footer.html")]
+pub struct ChapterFooter<'a> {
+    pub chapter: &'a ChapterContext<'a>,
+}
+
+impl EpubPage for ChapterFooter<'_> {
+    const VARIABLES: &'static [&'static str] = ChapterContext::VARIABLES;
+
+    fn values(&self) -> Vec<String> {
+        self.chapter.values()
+    }
+}
+
+/// Table of contents page, the same for every book.
+#[derive(Template)]
+#[template(path = "epub/toc.html")]
+pub struct TocPage<'a> {
+    pub title: &'a str,
+    // link to the chapter, and its label
+    pub entries: Vec<(String, String)>,
+}
+
+/// "About this export" page, at the end of the book.
+#[derive(Template)]
+#[template(path = "epub/colophon.html")]
+pub struct Colophon<'a> {
+    title: &'a str,
+    author: Option<&'a str>,
+    translator: Option<&'a str>,
+    range: Option<String>,
+    chapter_count: usize,
+    exported_on: String,
+}
+
+impl<'a> Colophon<'a> {
+    pub fn new(epub: &'a Epub, chapter_count: usize) -> Self {
+        Colophon {
+            title: &epub.title,
+            author: epub.author.as_deref(),
+            translator: epub.translator.as_deref(),
+            range: range(epub),
+            chapter_count,
+            exported_on: exported_on(epub),
+        }
+    }
+}
+
+impl EpubPage for Colophon<'_> {
+    const VARIABLES: &'static [&'static str] = &[
+        "title",
+        "author",
+        "translator",
+        "range",
+        "chapter_count",
+        "exported_on",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.title.to_owned(),
+            self.author.unwrap_or_default().to_owned(),
+            self.translator.unwrap_or_default().to_owned(),
+            self.range.clone().unwrap_or_default(),
+            self.chapter_count.to_string(),
+            self.exported_on.clone(),
+        ]
+    }
+}
+
+/// Variables used by `template` that aren't in `names`.
+pub fn unknown_variables<'a>(template: &'a str, names: &[&str]) -> Vec<&'a str> {
+    placeholders(template)
+        .into_iter()
+        .map(|(_, name)| name)
+        .filter(|name| !names.contains(name))
+        .collect()
+}
+
+fn substitute(template: &str, names: &[&str], values: &[String]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut last = 0;
+
+    for (placeholder, name) in placeholders(template) {
+        rendered.push_str(&template[last..placeholder.start]);
+        if let Some(idx) = names.iter().position(|n| *n == name) {
+            // variables can end up in attributes, such as the source's
+            rendered.push_str(&escape_attribute(&values[idx]));
+        }
+        last = placeholder.end;
+    }
+    rendered.push_str(&template[last..]);
+
+    rendered
+}
+
+// `{{ name }}` placeholders, with their range and trimmed name
+fn placeholders(template: &str) -> Vec<(Range<usize>, &str)> {
+    let mut placeholders = vec![];
+    let mut from = 0;
+
+    while let Some(start) = template[from..].find("{{").map(|start| from + start) {
+        let Some(end) = template[start..].find("}}").map(|end| start + end + 2) else {
+            break;
+        };
+        placeholders.push((start..end, template[start + 2..end - 2].trim()));
+        from = end;
+    }
+
+    placeholders
+}
+
+fn range(epub: &Epub) -> Option<String> {
+    epub.chapter_range.map(|(from, to)| match from == to {
+        true => from.to_string(),
+        false => format!("{from}–{to}"),
+    })
+}
+
+fn exported_on(epub: &Epub) -> String {
+    epub.exported_on.format("%B %-d, %Y").to_string()
+}
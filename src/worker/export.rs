@@ -0,0 +1,927 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use models::{
+    chapter::Footnote,
+    delivery::DeliveryStatus,
+    epub::{EpubChapter, Series, TocEntry},
+    export::{Export, ExportKinds, ExportOptions, ExportedChapters},
+};
+use sqlx::{postgres::types::PgInterval, PgPool};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::server::{
+    books::Book,
+    chapters::{content::to_html, Chapter},
+    exports::validate::{gaps, Invalid},
+};
+
+use super::{
+    artifact,
+    delivery::{Artifact, Delivery},
+    epub::{Epub, MyEpub, Part},
+    images::{Embedded, ImageFetcher},
+    retry::{Failure, RetryPolicy},
+    volume::{self, part_identifier, Volume, VolumeLimits},
+};
+
+/// An export resolved to the outline of its ePub.
+struct Prepared {
+    epub: Epub,
+    pages: Vec<Page>,
+    // chapter number of each page, to title volumes
+    numbers: Vec<i32>,
+    // what the export contains, for humans
+    description: String,
+    // highest chapter number per book id
+    exported_chapters: BTreeMap<i32, i32>,
+}
+
+// bounds used when an export wants every chapter of a book
+const ALL_CHAPTERS: (i32, i32) = (i32::MIN, i32::MAX);
+
+// most books come from English translation sites
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// A chapter, or the placeholder of a missing one, as it ends up in the ePub.
+///
+/// Preparing an export only loads the outline of its chapters, their content
+/// is streamed from the database while the ePub is written.
+pub struct Page {
+    pub number: i32,
+    pub title: String,
+    // of the content and footnotes as stored
+    pub size: usize,
+    // None for a missing chapter
+    pub(super) chapter_id: Option<i32>,
+}
+
+impl From<ChapterOutline> for Page {
+    fn from(chapter: ChapterOutline) -> Self {
+        Page {
+            number: chapter.number_in_book,
+            title: chapter.name,
+            size: chapter.size as usize,
+            chapter_id: Some(chapter.id),
+        }
+    }
+}
+
+/// A chapter without its content.
+struct ChapterOutline {
+    id: i32,
+    book_id: i32,
+    name: String,
+    number_in_book: i32,
+    size: i32,
+}
+
+// parts waiting for the ePub builder, bounding how much content is in memory
+const PARTS_BUFFER: usize = 4;
+
+/// What generating an export stored.
+struct Generated {
+    volumes: i32,
+    exported_chapters: BTreeMap<i32, i32>,
+}
+
+pub async fn run_export(
+    pool: &PgPool,
+    export: Export,
+    destinations: &[Box<dyn Delivery>],
+    fetcher: &dyn ImageFetcher,
+    policy: &RetryPolicy,
+    limits: VolumeLimits,
+) {
+    let Err(failure) = attempt(pool, &export, destinations, fetcher, policy, limits).await else {
+        return;
+    };
+
+    let updated = match policy.next_attempt(export.attempts, &failure) {
+        Some(delay) => {
+            tracing::warn!(
+                export_id = export.id,
+                "Export failed, retrying in {delay:?}: {failure}"
+            );
+            requeue(pool, export.id, delay).await
+        }
+        None => {
+            tracing::error!(export_id = export.id, "Export failed: {failure}");
+            sqlx::query!(
+                "UPDATE exports
+                SET processed_at = COALESCE(processed_at, CURRENT_TIMESTAMP),
+                    next_attempt_at = NULL,
+                    error = $2
+                WHERE id = $1",
+                export.id,
+                failure.to_string(),
+            )
+            .execute(pool)
+            .await
+            .map(|_| ())
+        }
+    };
+    // the lease runs out and the reaper takes it from there
+    if let Err(e) = updated {
+        tracing::error!(
+            "Failed to record the outcome of export {}: {:#}",
+            export.id,
+            e
+        );
+    }
+}
+
+/// Generates the export, or reuses its stored ePubs, and sends them to every
+/// destination that didn't get them yet.
+///
+/// When a delivery is to be retried the export is put back in the queue.
+/// Otherwise it's sent once any destination got it, and fails naming the
+/// destinations that gave up when none did.
+async fn attempt(
+    pool: &PgPool,
+    export: &Export,
+    destinations: &[Box<dyn Delivery>],
+    fetcher: &dyn ImageFetcher,
+    policy: &RetryPolicy,
+    limits: VolumeLimits,
+) -> Result<(), Failure> {
+    let volumes = load_or_generate(pool, export, fetcher, limits).await?;
+    sqlx::query!(
+        "UPDATE exports
+        SET processed_at = CURRENT_TIMESTAMP,
+            next_attempt_at = NULL
+        WHERE id = $1",
+        export.id,
+    )
+    .execute(pool)
+    .await?;
+
+    if destinations.is_empty() {
+        return Err(Failure::permanent("No delivery is configured"));
+    }
+
+    let mut any_sent = false;
+    let mut failed: Vec<&str> = vec![];
+    let mut next_attempt: Option<Duration> = None;
+    // volumes are sent as separate attachments or messages
+    for volume in volumes {
+        let artifact = artifact::load(pool, export.id, volume).await?;
+        for destination in destinations {
+            match deliver(pool, export.id, destination.as_ref(), &artifact, policy).await? {
+                Outcome::Sent => any_sent = true,
+                Outcome::RetryIn(delay) => {
+                    next_attempt = Some(next_attempt.map_or(delay, |next| next.min(delay)));
+                }
+                // recorded on its delivery, the other destinations still count
+                Outcome::Failed => {
+                    if !failed.contains(&destination.name()) {
+                        failed.push(destination.name());
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(delay) = next_attempt {
+        tracing::info!(
+            export_id = export.id,
+            "Export not fully delivered, retrying in {delay:?}"
+        );
+        requeue(pool, export.id, delay).await?;
+    } else if any_sent {
+        tracing::info!(export_id = export.id, "Epub sent");
+        sqlx::query!(
+            "UPDATE exports
+            SET sent = true
+            WHERE id = $1",
+            export.id,
+        )
+        .execute(pool)
+        .await?;
+        if let Err(e) = advance_watermarks(pool, export.id).await {
+            tracing::error!(
+                "Failed to advance watermarks of export {}: {:#}",
+                export.id,
+                e
+            );
+        }
+    } else {
+        return Err(Failure::permanent(format!(
+            "Not delivered to {}",
+            failed.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Makes sure every volume of the export is stored, generating them unless
+/// a previous attempt got to the end, and returns their numbers.
+async fn load_or_generate(
+    pool: &PgPool,
+    export: &Export,
+    fetcher: &dyn ImageFetcher,
+    limits: VolumeLimits,
+) -> Result<Vec<i32>, Failure> {
+    let volumes = artifact::volumes(pool, export.id).await?;
+    if export.volumes == Some(volumes.len() as i32) && !volumes.is_empty() {
+        tracing::info!(export_id = export.id, "Reusing the stored epubs");
+        return Ok(volumes);
+    }
+
+    // left by an attempt that stopped halfway
+    artifact::delete(pool, export.id).await?;
+    let generated = process(export.clone(), pool, fetcher, limits).await?;
+    sqlx::query!(
+        "UPDATE exports
+        SET exported_chapters = $2,
+            volumes = $3
+        WHERE id = $1",
+        export.id,
+        serde_json::to_value(ExportedChapters(generated.exported_chapters)).unwrap(),
+        generated.volumes,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok((1..=generated.volumes).collect())
+}
+
+/// Puts the export back in the queue, to be claimed again after `delay`.
+async fn requeue(pool: &PgPool, export_id: i32, delay: Duration) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE exports
+        SET processing_started_at = NULL,
+            heartbeat_at = NULL,
+            next_attempt_at = CURRENT_TIMESTAMP + $2::interval
+        WHERE id = $1",
+        export_id,
+        PgInterval::try_from(delay).expect("delay should fit in an interval"),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+enum Outcome {
+    Sent,
+    RetryIn(Duration),
+    // the destination gave up on this export
+    Failed,
+}
+
+/// Sends the artifact to one destination and records the outcome.
+async fn deliver(
+    pool: &PgPool,
+    export_id: i32,
+    destination: &dyn Delivery,
+    artifact: &Artifact,
+    policy: &RetryPolicy,
+) -> Result<Outcome, Failure> {
+    let status = sqlx::query_as!(
+        DeliveryStatus,
+        "INSERT INTO deliveries (export_id, destination, volume)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (export_id, destination, volume) DO UPDATE SET destination = EXCLUDED.destination
+        RETURNING *",
+        export_id,
+        destination.name(),
+        artifact.volume,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // a previous run already got it there, or gave up
+    if status.sent_at.is_some() {
+        return Ok(Outcome::Sent);
+    }
+    match status.next_attempt_at {
+        None if status.error.is_some() => return Ok(Outcome::Failed),
+        Some(at) if at > Utc::now() => {
+            return Ok(Outcome::RetryIn(
+                (at - Utc::now()).to_std().unwrap_or_default(),
+            ))
+        }
+        _ => {}
+    }
+
+    tracing::info!(export_id, "Sending epub to {}", destination.name());
+    let attempts = status.attempts + 1;
+    match destination.deliver(artifact).await {
+        Ok(()) => {
+            sqlx::query!(
+                "UPDATE deliveries
+                SET sent_at = CURRENT_TIMESTAMP,
+                    attempts = $2,
+                    next_attempt_at = NULL,
+                    error = NULL
+                WHERE id = $1",
+                status.id,
+                attempts,
+            )
+            .execute(pool)
+            .await?;
+            Ok(Outcome::Sent)
+        }
+        Err(failure) => {
+            tracing::warn!(
+                export_id,
+                "Epub not sent to {}: {failure}",
+                destination.name()
+            );
+            let next_attempt = policy.next_attempt(attempts, &failure);
+            sqlx::query!(
+                "UPDATE deliveries
+                SET attempts = $2,
+                    next_attempt_at = CURRENT_TIMESTAMP + $3::interval,
+                    error = $4
+                WHERE id = $1",
+                status.id,
+                attempts,
+                next_attempt.map(|delay| {
+                    PgInterval::try_from(delay).expect("delay should fit in an interval")
+                }),
+                failure.to_string(),
+            )
+            .execute(pool)
+            .await?;
+
+            Ok(match next_attempt {
+                Some(delay) => Outcome::RetryIn(delay),
+                None => Outcome::Failed,
+            })
+        }
+    }
+}
+
+async fn process(
+    export: Export,
+    pool: &PgPool,
+    fetcher: &dyn ImageFetcher,
+    limits: VolumeLimits,
+) -> Result<Generated, Failure> {
+    tracing::info!(export_id = export.id, "Processing export");
+
+    let prepared = prepare(pool, &export.meta, &export.options, export.user_id).await?;
+
+    let limits = VolumeLimits {
+        max_chapters: export
+            .options
+            .max_chapters
+            .map(|max| max.max(1) as usize)
+            .or(limits.max_chapters),
+        ..limits
+    };
+    let mut volumes = 0;
+    // each volume is stored as soon as it's generated, holding a single one
+    // in memory
+    for volume in volume::split(
+        prepared.epub,
+        prepared.pages,
+        &prepared.numbers,
+        prepared.description,
+        limits,
+    ) {
+        volumes += 1;
+        let title = volume.epub.title.clone();
+        let description = volume.description.clone();
+        let bytes = generate(pool, fetcher, volume).await?;
+
+        tracing::info!(
+            export_id = export.id,
+            "Epub generated: {title} ({} bytes)",
+            bytes.len()
+        );
+        let artifact = Artifact {
+            filename: format!("{} ({}).epub", title.replace(['/', '\\'], "_"), export.id),
+            title,
+            description,
+            volume: volumes,
+            bytes: bytes.into(),
+        };
+        artifact::store(pool, export.id, &artifact).await?;
+    }
+
+    Ok(Generated {
+        volumes,
+        exported_chapters: prepared.exported_chapters,
+    })
+}
+
+/// Generates the ePub of an export right away, without queuing it nor
+/// splitting it in volumes.
+///
+/// Returns `None`, before fetching any content, when the export is over
+/// `max_size` and should go through the queue instead.
+pub async fn generate_now(
+    pool: &PgPool,
+    fetcher: &dyn ImageFetcher,
+    meta: &ExportKinds,
+    options: &ExportOptions,
+    user_id: Option<i32>,
+    max_size: usize,
+) -> Result<Option<(String, Vec<u8>)>, Failure> {
+    let prepared = prepare(pool, meta, options, user_id).await?;
+    let size = volume::cover_size(&prepared.epub)
+        + prepared.pages.iter().map(volume::page_size).sum::<usize>();
+    if size > max_size {
+        return Ok(None);
+    }
+
+    let title = prepared.epub.title.clone();
+    let volume = Volume {
+        epub: prepared.epub,
+        pages: prepared.pages,
+        description: prepared.description,
+    };
+    let bytes = generate(pool, fetcher, volume).await?;
+
+    Ok(Some((title, bytes)))
+}
+
+/// Resolves an export to the outline of its ePub, styled as asked.
+async fn prepare(
+    pool: &PgPool,
+    meta: &ExportKinds,
+    options: &ExportOptions,
+    user_id: Option<i32>,
+) -> Result<Prepared, Failure> {
+    let mut prepared = outline(pool, meta, options, user_id).await?;
+    if prepared.pages.is_empty() {
+        return Err(Failure::permanent("no chapters to export"));
+    }
+    prepared.epub.style = options.style;
+    prepared.epub.grayscale_cover = options.grayscale_cover;
+    prepared.epub.toc_position = options.toc_position;
+    prepared.epub.numbered_toc = options.numbered_toc;
+    prepared.epub.custom_css = custom_css(pool, user_id, prepared.epub.custom_css.take()).await?;
+
+    Ok(prepared)
+}
+
+async fn outline(
+    pool: &PgPool,
+    meta: &ExportKinds,
+    options: &ExportOptions,
+    user_id: Option<i32>,
+) -> Result<Prepared, Failure> {
+    match meta {
+        ExportKinds::ChaptersRange { book_id, chapters } => {
+            let book = fetch_book(pool, *book_id).await?;
+            let outlines = fetch_chapters(pool, *book_id, *chapters).await?;
+            let exported_chapters = last_chapter(*book_id, &outlines);
+            let pages = pages(&book, outlines, Some(*chapters), options)?;
+            let (epub, numbers) = book_epub(book, &pages, true);
+
+            Ok(Prepared {
+                epub,
+                pages,
+                numbers,
+                description: format!("From chapter {} to chapter {}", chapters.0, chapters.1),
+                exported_chapters,
+            })
+        }
+        ExportKinds::FullBook(book_id) => {
+            let book = fetch_book(pool, *book_id).await?;
+            let outlines = fetch_chapters(pool, *book_id, ALL_CHAPTERS).await?;
+            let exported_chapters = last_chapter(*book_id, &outlines);
+            let pages = pages(&book, outlines, None, options)?;
+            let (epub, numbers) = book_epub(book, &pages, false);
+
+            Ok(Prepared {
+                description: format!("Full book, {} chapters", numbers.len()),
+                epub,
+                pages,
+                numbers,
+                exported_chapters,
+            })
+        }
+        ExportKinds::SinceLastExport(book_id) => {
+            let book = fetch_book(pool, *book_id).await?;
+            let watermark = match user_id {
+                Some(user_id) => fetch_watermark(pool, user_id, *book_id).await?,
+                None => None,
+            };
+            let from = watermark.map_or(i32::MIN, |last| last.saturating_add(1));
+            let outlines = fetch_chapters(pool, *book_id, (from, i32::MAX)).await?;
+            let exported_chapters = last_chapter(*book_id, &outlines);
+            // a gap right after the watermark is still a gap
+            let range = watermark
+                .zip(exported_chapters.get(book_id))
+                .map(|(_, last)| (from, *last));
+            let pages = pages(&book, outlines, range, options)?;
+            let (epub, numbers) = book_epub(book, &pages, true);
+
+            Ok(Prepared {
+                description: match watermark {
+                    Some(last) => format!("{} chapters after chapter {last}", numbers.len()),
+                    None => format!("First export, {} chapters", numbers.len()),
+                },
+                epub,
+                pages,
+                numbers,
+                exported_chapters,
+            })
+        }
+        ExportKinds::SingleChapter(chapter_id) => {
+            let not_found = Failure::permanent(format!("chapter {chapter_id} not found"));
+            let chapter = sqlx::query_as!(
+                ChapterOutline,
+                r#"SELECT id, book_id, name, number_in_book,
+                    octet_length(content) + octet_length(footnotes::text) AS "size!"
+                FROM chapters
+                WHERE id = $1"#,
+                chapter_id
+            )
+            .fetch_optional(pool)
+            .await?
+            .ok_or(not_found)?;
+            let book = fetch_book(pool, chapter.book_id).await?;
+            let description = format!("Chapter {}: {}", chapter.number_in_book, chapter.name);
+            let exported_chapters = last_chapter(book.id, std::slice::from_ref(&chapter));
+            let pages = vec![chapter.into()];
+            let (epub, numbers) = book_epub(book, &pages, true);
+
+            Ok(Prepared {
+                description,
+                exported_chapters,
+                epub,
+                pages,
+                numbers,
+            })
+        }
+        ExportKinds::Anthology { title, parts } => {
+            let mut authors: Vec<String> = vec![];
+            let mut translators: Vec<String> = vec![];
+            let mut languages: Vec<String> = vec![];
+            let mut subjects: Vec<String> = vec![];
+            let mut pages: Vec<Page> = vec![];
+            let mut exported_chapters = BTreeMap::new();
+
+            for part in parts {
+                let book = fetch_book(pool, part.book_id).await?;
+                let range = part.chapters.unwrap_or(ALL_CHAPTERS);
+                let outlines = fetch_chapters(pool, part.book_id, range).await?;
+                for (book_id, last) in last_chapter(part.book_id, &outlines) {
+                    let highest = exported_chapters.entry(book_id).or_insert(last);
+                    *highest = last.max(*highest);
+                }
+                let book_pages = self::pages(&book, outlines, part.chapters, options)?;
+
+                if let Some(author) = book.author.filter(|a| !authors.contains(a)) {
+                    authors.push(author);
+                }
+                if let Some(translator) = book.translator.filter(|t| !translators.contains(t)) {
+                    translators.push(translator);
+                }
+                if let Some(language) = book.language.filter(|l| !languages.contains(l)) {
+                    languages.push(language);
+                }
+                for subject in book.subjects {
+                    if !subjects.contains(&subject) {
+                        subjects.push(subject);
+                    }
+                }
+                pages.extend(book_pages.into_iter().map(|page| Page {
+                    title: format!("{} - {}", book.name, page.title),
+                    ..page
+                }));
+            }
+
+            Ok(Prepared {
+                description: format!("{} chapters from {} books", pages.len(), parts.len()),
+                // chapter numbers of different books would be meaningless
+                numbers: (1..=pages.len() as i32).collect(),
+                pages,
+                exported_chapters,
+                epub: Epub {
+                    title: title.clone(),
+                    author: (!authors.is_empty()).then(|| authors.join(", ")),
+                    translator: (!translators.is_empty()).then(|| translators.join(", ")),
+                    cover: None,
+                    grayscale_cover: false,
+                    style: Default::default(),
+                    toc_position: Default::default(),
+                    numbered_toc: false,
+                    custom_css: None,
+                    // the same title is the same anthology, updated
+                    identifier: Uuid::new_v5(&Uuid::NAMESPACE_OID, title.as_bytes()),
+                    language: languages
+                        .into_iter()
+                        .next()
+                        .unwrap_or(DEFAULT_LANGUAGE.to_owned()),
+                    description: None,
+                    subjects,
+                    series: None,
+                    chapter_range: None,
+                    exported_on: Utc::now().date_naive(),
+                    templates: Default::default(),
+                },
+            })
+        }
+    }
+}
+
+/// `partial` exports only hold some chapters of the book: they get their own
+/// identifier and are put in a series named after the book.
+fn book_epub(book: Book, pages: &[Page], partial: bool) -> (Epub, Vec<i32>) {
+    let numbers: Vec<i32> = pages.iter().map(|page| page.number).collect();
+    let (identifier, series, chapter_range) = match (partial, numbers.first(), numbers.last()) {
+        (true, Some(from), Some(to)) => (
+            part_identifier(&book.identifier, *from, *to),
+            Some(Series {
+                name: book.name.clone(),
+                index: *from,
+            }),
+            Some((*from, *to)),
+        ),
+        _ => (book.identifier, None, None),
+    };
+    let epub = Epub {
+        title: book.name,
+        author: book.author,
+        translator: book.translator,
+        cover: book.cover,
+        grayscale_cover: false,
+        style: Default::default(),
+        toc_position: Default::default(),
+        numbered_toc: false,
+        custom_css: book.custom_css,
+        identifier,
+        language: book.language.unwrap_or(DEFAULT_LANGUAGE.to_owned()),
+        description: book.description,
+        subjects: book.subjects,
+        series,
+        chapter_range,
+        exported_on: Utc::now().date_naive(),
+        templates: book.epub_templates,
+    };
+
+    (epub, numbers)
+}
+
+/// Turns chapters into pages, checking the range has no gaps, or filling
+/// them with placeholders when they are allowed.
+///
+/// A whole book, without a range, goes from its first chapter to its last.
+fn pages(
+    book: &Book,
+    chapters: Vec<ChapterOutline>,
+    range: Option<(i32, i32)>,
+    options: &ExportOptions,
+) -> Result<Vec<Page>, Failure> {
+    let numbers: Vec<i32> = chapters.iter().map(|c| c.number_in_book).collect();
+    let range = match (range, numbers.first(), numbers.last()) {
+        (Some(range), _, _) => range,
+        (None, Some(first), Some(last)) => (*first, *last),
+        (None, _, _) => return Ok(vec![]),
+    };
+    let missing = gaps(&numbers, range);
+
+    if !missing.is_empty() && !options.allow_gaps {
+        let missing = Invalid::MissingChapters {
+            book_id: book.id,
+            missing,
+        };
+        return Err(Failure::permanent(missing.to_string()));
+    }
+
+    let mut pages = vec![];
+    let mut missing = missing.into_iter().peekable();
+    for chapter in chapters {
+        while let Some((from, to)) = missing.next_if(|(from, _)| *from < chapter.number_in_book) {
+            pages.extend((from..=to).map(placeholder));
+        }
+        pages.push(chapter.into());
+    }
+    for (from, to) in missing {
+        pages.extend((from..=to).map(placeholder));
+    }
+
+    Ok(pages)
+}
+
+// chapters come sorted by number
+fn last_chapter(book_id: i32, chapters: &[ChapterOutline]) -> BTreeMap<i32, i32> {
+    chapters
+        .last()
+        .map(|chapter| (book_id, chapter.number_in_book))
+        .into_iter()
+        .collect()
+}
+
+fn placeholder(number: i32) -> Page {
+    Page {
+        number,
+        title: format!("Chapter {number}"),
+        size: 0,
+        chapter_id: None,
+    }
+}
+
+/// Writes the ePub of a volume, streaming its chapters from the database
+/// into the builder as it goes.
+///
+/// The builder runs on the blocking pool, zipping and image processing are
+/// CPU bound and the runtime also serves HTTP.
+async fn generate(
+    pool: &PgPool,
+    fetcher: &dyn ImageFetcher,
+    volume: Volume,
+) -> Result<Vec<u8>, Failure> {
+    let (sender, mut receiver) = mpsc::channel(PARTS_BUFFER);
+    let epub = MyEpub(volume.epub);
+    let contents: Vec<TocEntry> = volume
+        .pages
+        .iter()
+        .map(|page| TocEntry {
+            number: page.number,
+            title: page.title.clone(),
+        })
+        .collect();
+    let generation = tokio::task::spawn_blocking(move || {
+        epub.to_bytes(&contents, std::iter::from_fn(|| receiver.blocking_recv()))
+    });
+
+    let streamed = stream_pages(pool, fetcher, &volume.pages, sender).await;
+    let bytes = generation
+        .await
+        .map_err(|e| Failure::permanent(format!("ePub generation panicked: {e}")))??;
+    // the ePub misses the chapters that could not be read
+    streamed?;
+
+    Ok(bytes)
+}
+
+async fn stream_pages(
+    pool: &PgPool,
+    fetcher: &dyn ImageFetcher,
+    pages: &[Page],
+    sender: mpsc::Sender<Part>,
+) -> Result<(), Failure> {
+    let ids: Vec<i32> = pages.iter().filter_map(|page| page.chapter_id).collect();
+    let mut chapters = sqlx::query_as!(
+        Chapter,
+        "SELECT * FROM chapters
+        WHERE id = ANY($1)
+        ORDER BY array_position($1, id)",
+        &ids,
+    )
+    .fetch(pool);
+    let mut images = Embedded::default();
+
+    for page in pages {
+        let mut chapter = match page.chapter_id {
+            Some(id) => {
+                let chapter = chapters
+                    .try_next()
+                    .await?
+                    .filter(|chapter| chapter.id == id)
+                    .ok_or(Failure::retryable(format!("chapter {id} was deleted")))?;
+                epub_chapter(page, chapter)
+            }
+            None => EpubChapter {
+                title: page.title.clone(),
+                number: page.number,
+                url: None,
+                content: format!("<p><em>Chapter {} is missing.</em></p>", page.number),
+                footnotes: vec![],
+            },
+        };
+
+        let mut parts: Vec<Part> = images
+            .embed(fetcher, &mut chapter)
+            .await
+            .into_iter()
+            .map(Part::Image)
+            .collect();
+        parts.push(Part::Chapter(chapter));
+        for part in parts {
+            // the generation failed, its error is the one reported
+            if sender.send(part).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn epub_chapter(page: &Page, chapter: Chapter) -> EpubChapter {
+    let footnotes = chapter
+        .footnotes
+        .0
+        .into_iter()
+        .map(|footnote| Footnote {
+            content: to_html(&footnote.content, chapter.html),
+            ..footnote
+        })
+        .collect();
+
+    EpubChapter {
+        title: page.title.clone(),
+        number: page.number,
+        url: chapter.url,
+        content: to_html(&chapter.content, chapter.html),
+        footnotes,
+    }
+}
+
+async fn fetch_book(pool: &PgPool, book_id: i32) -> Result<Book, Failure> {
+    sqlx::query_as!(
+        Book,
+        "SELECT *
+        FROM books
+        WHERE id = $1",
+        book_id,
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(Failure::permanent(format!("book {book_id} not found")))
+}
+
+async fn fetch_chapters(
+    pool: &PgPool,
+    book_id: i32,
+    chapters: (i32, i32),
+) -> Result<Vec<ChapterOutline>, Failure> {
+    sqlx::query_as!(
+        ChapterOutline,
+        r#"SELECT id, book_id, name, number_in_book,
+            octet_length(content) + octet_length(footnotes::text) AS "size!"
+        FROM chapters
+        WHERE book_id = $1
+           AND number_in_book >= $2
+           AND number_in_book <= $3
+        ORDER BY number_in_book ASC"#,
+        book_id,
+        chapters.0,
+        chapters.1,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Failure::from)
+}
+
+/// The user's CSS followed by the book's, so a book can override the
+/// user's defaults.
+async fn custom_css(
+    pool: &PgPool,
+    user_id: Option<i32>,
+    book_css: Option<String>,
+) -> Result<Option<String>, sqlx::Error> {
+    let user_css = match user_id {
+        Some(user_id) => sqlx::query_scalar!("SELECT custom_css FROM users WHERE id = $1", user_id)
+            .fetch_optional(pool)
+            .await?
+            .flatten(),
+        None => None,
+    };
+    let css = [user_css, book_css]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<String>>();
+
+    Ok((!css.is_empty()).then(|| css.join("\n")))
+}
+
+async fn fetch_watermark(
+    pool: &PgPool,
+    user_id: i32,
+    book_id: i32,
+) -> Result<Option<i32>, Failure> {
+    sqlx::query_scalar!(
+        "SELECT last_chapter FROM watermarks
+        WHERE user_id = $1
+            AND book_id = $2",
+        user_id,
+        book_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(Failure::from)
+}
+
+/// Moves the watermarks of the export's user up to the chapters it contained,
+/// once it reached every destination.
+async fn advance_watermarks(pool: &PgPool, export_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO watermarks (user_id, book_id, last_chapter)
+        SELECT e.user_id, c.key::int, c.value::int
+        FROM exports e, jsonb_each_text(e.exported_chapters) c
+        WHERE e.id = $1
+            AND e.user_id IS NOT NULL
+            AND c.key::int IN (SELECT id FROM books)
+        ON CONFLICT (user_id, book_id) DO UPDATE
+        SET last_chapter = GREATEST(watermarks.last_chapter, EXCLUDED.last_chapter),
+            updated_at = CURRENT_TIMESTAMP",
+        export_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
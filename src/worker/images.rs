@@ -0,0 +1,471 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Cursor,
+    ops::Range,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use axum::async_trait;
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageFormat};
+use models::epub::{EpubChapter, EpubImage};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::epub::html::tag_end;
+
+// bigger images are left out, a chapter is not a photo album
+const MAX_IMAGE_SIZE: usize = 10 * 1024 * 1024;
+
+// same as covers
+const JPEG_QUALITY: u8 = 85;
+
+/// Where the images referenced in chapters are downloaded from.
+#[async_trait]
+pub trait ImageFetcher: Send + Sync {
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>>;
+}
+
+/// Downloads images over HTTP(S), from the sites chapters were read on.
+pub struct HttpFetcher {
+    client: reqwest::Client,
+}
+
+impl HttpFetcher {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("HTTP client should build");
+
+        HttpFetcher { client }
+    }
+}
+
+#[async_trait]
+impl ImageFetcher for HttpFetcher {
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        let mut res = self.client.get(url).send().await?.error_for_status()?;
+        if res.content_length().unwrap_or_default() as usize > MAX_IMAGE_SIZE {
+            bail!("image is over {MAX_IMAGE_SIZE} bytes");
+        }
+        // the length isn't always announced, nor right
+        let mut bytes = vec![];
+        while let Some(chunk) = res.chunk().await? {
+            if bytes.len() + chunk.len() > MAX_IMAGE_SIZE {
+                bail!("image is over {MAX_IMAGE_SIZE} bytes");
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Images embedded in the ePub being generated, so each one is fetched and
+/// stored once.
+///
+/// Images are named after the hash of their content, so the same image is
+/// stored once whatever the number of chapters, or URLs, using it. Images
+/// failing to download, or that aren't one, are dropped from the chapters
+/// instead of failing the export.
+///
+/// Relative sources are resolved against the URL the chapter was read on,
+/// and left out for chapters without one.
+#[derive(Default)]
+pub struct Embedded {
+    // filename of every absolute URL, None when it could not be embedded
+    filenames: HashMap<String, Option<String>>,
+    stored: HashSet<String>,
+}
+
+impl Embedded {
+    /// Fetches the images `chapter` references and points its `<img>` tags
+    /// at them, returning the ones the ePub doesn't hold yet.
+    pub async fn embed(
+        &mut self,
+        fetcher: &dyn ImageFetcher,
+        chapter: &mut EpubChapter,
+    ) -> Vec<EpubImage> {
+        let mut images = vec![];
+        if contents(chapter).all(|html| img_tags(html).next().is_none()) {
+            return images;
+        }
+
+        let base = chapter.url.as_deref().and_then(|url| Url::parse(url).ok());
+        let urls: Vec<String> = contents(chapter)
+            .flat_map(sources)
+            .filter_map(|src| resolve(&src, base.as_ref()))
+            .collect();
+
+        for url in urls {
+            if self.filenames.contains_key(&url) {
+                continue;
+            }
+            let image = match fetch(fetcher, &url).await {
+                Ok(image) => image,
+                Err(e) => {
+                    tracing::warn!("Image {url} left out: {e:#}");
+                    self.filenames.insert(url, None);
+                    continue;
+                }
+            };
+            self.filenames.insert(url, Some(image.filename.clone()));
+            if self.stored.insert(image.filename.clone()) {
+                images.push(image);
+            }
+        }
+
+        chapter.content = rewrite(&chapter.content, base.as_ref(), &self.filenames);
+        for footnote in &mut chapter.footnotes {
+            footnote.content = rewrite(&footnote.content, base.as_ref(), &self.filenames);
+        }
+
+        images
+    }
+}
+
+async fn fetch(fetcher: &dyn ImageFetcher, url: &str) -> Result<EpubImage> {
+    let bytes = fetcher.fetch(url).await?;
+    let (mime, extension, bytes) = match image::guess_format(&bytes).context("not an image")? {
+        ImageFormat::Jpeg => ("image/jpeg", "jpg", bytes),
+        ImageFormat::Png => ("image/png", "png", bytes),
+        ImageFormat::Gif => ("image/gif", "gif", bytes),
+        // Kindles only read the formats above
+        ImageFormat::WebP => tokio::task::spawn_blocking(move || convert_webp(&bytes))
+            .await
+            .context("conversion panicked")??,
+        format => bail!("unsupported image format {format:?}"),
+    };
+    let hash = Sha256::digest(&bytes);
+    let hash: String = hash[..16].iter().map(|b| format!("{b:02x}")).collect();
+
+    Ok(EpubImage {
+        filename: format!("images/{hash}.{extension}"),
+        mime,
+        bytes,
+    })
+}
+
+// to a PNG when it has transparency, which JPEGs don't
+fn convert_webp(bytes: &[u8]) -> Result<(&'static str, &'static str, Vec<u8>)> {
+    let image = image::load_from_memory_with_format(bytes, ImageFormat::WebP)
+        .context("WebP image cannot be decoded")?;
+    let mut converted = Cursor::new(vec![]);
+
+    // lossless WebPs have an alpha channel, even fully opaque
+    let transparent = match image.as_rgba8() {
+        Some(rgba) => rgba.pixels().any(|pixel| pixel[3] < u8::MAX),
+        None => image.color().has_alpha(),
+    };
+    if transparent {
+        image
+            .write_to(&mut converted, ImageFormat::Png)
+            .context("WebP image cannot be converted")?;
+        return Ok(("image/png", "png", converted.into_inner()));
+    }
+
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut converted, JPEG_QUALITY))
+        .context("WebP image cannot be converted")?;
+    Ok(("image/jpeg", "jpg", converted.into_inner()))
+}
+
+// absolute URL of an image source, relative ones need the chapter's URL
+fn resolve(src: &str, base: Option<&Url>) -> Option<String> {
+    let url = match base {
+        Some(base) => base.join(src).ok()?,
+        None => Url::parse(src).ok()?,
+    };
+
+    matches!(url.scheme(), "http" | "https").then(|| url.into())
+}
+
+fn contents(chapter: &EpubChapter) -> impl Iterator<Item = &str> {
+    std::iter::once(chapter.content.as_str())
+        .chain(chapter.footnotes.iter().map(|f| f.content.as_str()))
+}
+
+// `<img>` tags, with the range of the tag and of its source
+//
+// Chapter HTML is sanitized, so attributes are double quoted with quotes
+// escaped inside them, but not `>`.
+fn img_tags(html: &str) -> impl Iterator<Item = (Range<usize>, Range<usize>)> + '_ {
+    html.match_indices("<img ").filter_map(|(start, _)| {
+        let tag = start..start + tag_end(&html[start..]);
+        let src = tag.start + html[tag.clone()].find(" src=\"")? + 6;
+        let src = src..src + html[src..tag.end].find('"')?;
+
+        Some((tag, src))
+    })
+}
+
+fn sources(html: &str) -> Vec<String> {
+    img_tags(html)
+        .map(|(_, src)| unescape(&html[src]))
+        .collect()
+}
+
+// points `<img>` tags at the embedded images, removing the ones left out
+fn rewrite(html: &str, base: Option<&Url>, filenames: &HashMap<String, Option<String>>) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut last = 0;
+
+    for (tag, src) in img_tags(html) {
+        let url = resolve(&unescape(&html[src.clone()]), base);
+        match url.and_then(|url| filenames.get(&url)) {
+            Some(Some(filename)) => {
+                rewritten.push_str(&html[last..src.start]);
+                rewritten.push_str(filename);
+                last = src.end;
+            }
+            _ => {
+                rewritten.push_str(&html[last..tag.start]);
+                last = tag.end;
+            }
+        }
+    }
+    rewritten.push_str(&html[last..]);
+
+    rewritten
+}
+
+fn unescape(attribute: &str) -> String {
+    attribute.replace("&quot;", "\"").replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use image::{codecs::webp::WebPEncoder, ImageBuffer, Rgba};
+    use models::chapter::Footnote;
+
+    use super::*;
+
+    // serves images by URL, and remembers what was asked
+    #[derive(Default)]
+    struct StubFetcher {
+        images: HashMap<String, Vec<u8>>,
+        fetched: Mutex<Vec<String>>,
+    }
+
+    impl StubFetcher {
+        fn with(images: &[(&str, Vec<u8>)]) -> Self {
+            StubFetcher {
+                images: images
+                    .iter()
+                    .map(|(url, bytes)| (url.to_string(), bytes.clone()))
+                    .collect(),
+                ..Default::default()
+            }
+        }
+
+        fn fetched(&self) -> Vec<String> {
+            self.fetched.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl ImageFetcher for StubFetcher {
+        async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+            self.fetched.lock().unwrap().push(url.to_owned());
+            match self.images.get(url) {
+                Some(bytes) => Ok(bytes.clone()),
+                None => bail!("404"),
+            }
+        }
+    }
+
+    fn encoded(alpha: u8, format: ImageFormat) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(2, 2, Rgba([200, 10, 10, alpha]));
+        let mut bytes = Cursor::new(vec![]);
+        match format {
+            ImageFormat::WebP => image
+                .write_with_encoder(WebPEncoder::new_lossless(&mut bytes))
+                .unwrap(),
+            format => image.write_to(&mut bytes, format).unwrap(),
+        }
+        bytes.into_inner()
+    }
+
+    fn png() -> Vec<u8> {
+        encoded(255, ImageFormat::Png)
+    }
+
+    fn chapter(url: Option<&str>, content: &str) -> EpubChapter {
+        EpubChapter {
+            title: "Chapter 1".to_owned(),
+            number: 1,
+            url: url.map(str::to_owned),
+            content: content.to_owned(),
+            footnotes: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn relative_sources_are_resolved_against_the_chapter() {
+        let fetcher = StubFetcher::with(&[
+            ("https://site.com/novel/img/a.png", png()),
+            ("https://cdn.com/b.png", encoded(128, ImageFormat::Png)),
+        ]);
+        let mut chapter = chapter(
+            Some("https://site.com/novel/chapter-1"),
+            r#"<p><img src="img/a.png"></p><p><img src="//cdn.com/b.png"></p>"#,
+        );
+
+        let images = Embedded::default().embed(&fetcher, &mut chapter).await;
+
+        assert_eq!(
+            fetcher.fetched(),
+            vec!["https://site.com/novel/img/a.png", "https://cdn.com/b.png"]
+        );
+        assert_eq!(images.len(), 2);
+        assert_eq!(
+            chapter.content,
+            format!(
+                r#"<p><img src="{}"></p><p><img src="{}"></p>"#,
+                images[0].filename, images[1].filename
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn relative_sources_without_chapter_url_are_left_out() {
+        let fetcher = StubFetcher::default();
+        let mut chapter = chapter(None, r#"<p>Text<img src="/a.png" alt="a"></p>"#);
+
+        let images = Embedded::default().embed(&fetcher, &mut chapter).await;
+
+        assert!(images.is_empty());
+        assert!(fetcher.fetched().is_empty());
+        assert_eq!(chapter.content, "<p>Text</p>");
+    }
+
+    #[tokio::test]
+    async fn alt_text_can_hold_a_closing_bracket() {
+        let fetcher = StubFetcher::with(&[("https://site.com/a.png", png())]);
+        let mut chapter = chapter(
+            None,
+            r#"<p><img alt="a > b" src="https://site.com/a.png">c > d</p>"#,
+        );
+
+        let images = Embedded::default().embed(&fetcher, &mut chapter).await;
+
+        assert_eq!(fetcher.fetched(), vec!["https://site.com/a.png"]);
+        assert_eq!(
+            chapter.content,
+            format!(
+                r#"<p><img alt="a > b" src="{}">c > d</p>"#,
+                images[0].filename
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn images_are_stored_once() {
+        let fetcher = StubFetcher::with(&[
+            ("https://site.com/a.png", png()),
+            ("https://mirror.com/a.png", png()),
+        ]);
+        let mut embedded = Embedded::default();
+        let mut first = chapter(
+            None,
+            r#"<img src="https://site.com/a.png"><img src="https://site.com/a.png">"#,
+        );
+        let mut second = chapter(None, r#"<img src="https://mirror.com/a.png">"#);
+        second.footnotes.push(Footnote {
+            marker: "1".to_owned(),
+            content: r#"<img src="https://site.com/a.png">"#.to_owned(),
+        });
+
+        let images = embedded.embed(&fetcher, &mut first).await;
+        assert_eq!(images.len(), 1);
+        let filename = &images[0].filename;
+        assert_eq!(images[0].mime, "image/png");
+        assert!(filename.starts_with("images/") && filename.ends_with(".png"));
+
+        assert!(embedded.embed(&fetcher, &mut second).await.is_empty());
+        assert_eq!(
+            fetcher.fetched(),
+            vec!["https://site.com/a.png", "https://mirror.com/a.png"]
+        );
+        assert_eq!(second.content, format!(r#"<img src="{filename}">"#));
+        assert_eq!(
+            second.footnotes[0].content,
+            format!(r#"<img src="{filename}">"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_images_are_left_out() {
+        let fetcher = StubFetcher::with(&[("https://site.com/text", b"<html>".to_vec())]);
+        let mut chapter = chapter(
+            None,
+            r#"<p>A<img src="https://site.com/missing.png">B<img src="https://site.com/text"></p>"#,
+        );
+
+        let images = Embedded::default().embed(&fetcher, &mut chapter).await;
+
+        assert!(images.is_empty());
+        assert_eq!(chapter.content, "<p>AB</p>");
+    }
+
+    #[tokio::test]
+    async fn webp_is_converted() {
+        let fetcher = StubFetcher::with(&[
+            (
+                "https://site.com/opaque.webp",
+                encoded(255, ImageFormat::WebP),
+            ),
+            ("https://site.com/clear.webp", encoded(0, ImageFormat::WebP)),
+        ]);
+        let mut chapter = chapter(
+            Some("https://site.com/chapter-1"),
+            r#"<img src="opaque.webp"><img src="clear.webp">"#,
+        );
+
+        let images = Embedded::default().embed(&fetcher, &mut chapter).await;
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].mime, "image/jpeg");
+        assert!(images[0].filename.ends_with(".jpg"));
+        assert_eq!(
+            image::guess_format(&images[0].bytes).unwrap(),
+            ImageFormat::Jpeg
+        );
+        assert_eq!(images[1].mime, "image/png");
+        assert!(images[1].filename.ends_with(".png"));
+        assert_eq!(
+            image::guess_format(&images[1].bytes).unwrap(),
+            ImageFormat::Png
+        );
+    }
+
+    #[test]
+    fn rewrite_matches_escaped_sources() {
+        let filenames = HashMap::from([(
+            "https://site.com/a.png?w=1&h=2".to_owned(),
+            Some("images/a.png".to_owned()),
+        )]);
+
+        assert_eq!(
+            rewrite(
+                r#"<p><img alt="x" src="/a.png?w=1&amp;h=2"> text</p>"#,
+                Some(&Url::parse("https://site.com/chapter").unwrap()),
+                &filenames,
+            ),
+            r#"<p><img alt="x" src="images/a.png"> text</p>"#
+        );
+    }
+
+    #[test]
+    fn only_web_urls_are_fetched() {
+        assert_eq!(resolve("ftp://site.com/a.png", None), None);
+        assert_eq!(resolve("a.png", None), None);
+        assert_eq!(
+            resolve("https://site.com/a.png", None).as_deref(),
+            Some("https://site.com/a.png")
+        );
+    }
+}
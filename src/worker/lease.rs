@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use sqlx::{postgres::types::PgInterval, PgPool};
+use tokio::task::JoinHandle;
+
+/// Keeps the lease on an export alive while the worker is processing it.
+///
+/// The returned task must be aborted once the export is done.
+pub fn heartbeat(pool: PgPool, export_id: i32, lease: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(lease / 3);
+        loop {
+            interval.tick().await;
+            if let Err(e) = sqlx::query!(
+                "UPDATE exports
+                SET heartbeat_at = CURRENT_TIMESTAMP
+                WHERE id = $1",
+                export_id,
+            )
+            .execute(&pool)
+            .await
+            {
+                tracing::warn!("Failed to refresh lease of export {}: {:#}", export_id, e);
+            }
+        }
+    })
+}
+
+/// Releases exports whose worker stopped sending heartbeats.
+///
+/// That includes exports claimed again to retry a delivery, which are
+/// already processed. Exports that still have attempts left go back to the
+/// queue, the others are marked as failed.
+pub async fn reap(pool: &PgPool, lease: Duration, max_attempts: i32) -> Result<(), sqlx::Error> {
+    let lease = PgInterval::try_from(lease).expect("lease should fit in an interval");
+
+    let requeued = sqlx::query!(
+        "UPDATE exports
+        SET processing_started_at = NULL,
+            heartbeat_at = NULL
+        WHERE sent = false
+            AND error IS NULL
+            AND processing_started_at IS NOT NULL
+            AND COALESCE(heartbeat_at, processing_started_at) < CURRENT_TIMESTAMP - $1::interval
+            AND attempts < $2
+        RETURNING id",
+        lease,
+        max_attempts,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let failed = sqlx::query!(
+        "UPDATE exports
+        SET processed_at = COALESCE(processed_at, CURRENT_TIMESTAMP),
+            next_attempt_at = NULL,
+            error = 'Abandoned after ' || attempts || ' attempts'
+        WHERE sent = false
+            AND error IS NULL
+            AND processing_started_at IS NOT NULL
+            AND COALESCE(heartbeat_at, processing_started_at) < CURRENT_TIMESTAMP - $1::interval
+            AND attempts >= $2
+        RETURNING id",
+        lease,
+        max_attempts,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in requeued {
+        tracing::warn!("Export {} lost its worker, back to the queue", row.id);
+    }
+    for row in failed {
+        tracing::warn!(
+            "Export {} lost its worker too many times, failing it",
+            row.id
+        );
+    }
+
+    Ok(())
+}
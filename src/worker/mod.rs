@@ -0,0 +1,166 @@
+mod artifact;
+mod delivery;
+pub mod epub;
+pub mod export;
+pub mod images;
+mod lease;
+mod retry;
+mod scheduler;
+mod volume;
+
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use models::export::Export;
+use sqlx::PgPool;
+
+use self::{delivery::DeliveryArgs, images::HttpFetcher, retry::RetryPolicy, volume::VolumeLimits};
+use super::{env::Environment, pool, signal::shutdown_signal};
+
+#[derive(Debug, Args)]
+pub struct WorkerArgs {
+    /// Seconds to wait between two polls when the queue is empty
+    #[arg(long, default_value_t = 5)]
+    poll_interval: u64,
+    /// Seconds without heartbeat after which an export is considered orphaned
+    #[arg(long, default_value_t = 60)]
+    lease_timeout: u64,
+    /// Number of times an export is generated, or sent to a destination, before giving up on it
+    #[arg(long, default_value_t = 5)]
+    max_attempts: i32,
+    /// Seconds to wait before the first retry, doubled after every failed attempt
+    #[arg(long, default_value_t = 30)]
+    retry_delay: u64,
+    /// Upper bound, in seconds, of the delay between two attempts
+    #[arg(long, default_value_t = 3600)]
+    max_retry_delay: u64,
+    /// Seconds between two checks of the subscriptions
+    #[arg(long, default_value_t = 60)]
+    schedule_interval: u64,
+    /// Size, in MB, above which an export is split in volumes (Discord caps attachments at 25MB)
+    #[arg(long, default_value_t = 20)]
+    max_volume_size: usize,
+    /// Number of chapters above which an export is split in volumes
+    #[arg(long)]
+    max_volume_chapters: Option<usize>,
+    /// Days generated ePubs are kept for, to be downloaded or sent again
+    #[arg(long, default_value_t = 30)]
+    artifact_retention_days: u64,
+    #[command(flatten)]
+    delivery: DeliveryArgs,
+}
+
+#[tokio::main]
+pub async fn start(env: Environment, args: WorkerArgs) {
+    let destinations = args
+        .delivery
+        .destinations(env.discord_webhook.clone())
+        .expect("Invalid delivery configuration");
+    if destinations.is_empty() {
+        tracing::warn!("No delivery configured, exports will fail once generated");
+    }
+    let fetcher = HttpFetcher::new();
+    let pool = pool::mk_pool(env.database_url.clone()).await;
+    let mut shutdown = std::pin::pin!(shutdown_signal());
+
+    let poll_interval = Duration::from_secs(args.poll_interval);
+    let lease = Duration::from_secs(args.lease_timeout);
+    let schedule_interval = Duration::from_secs(args.schedule_interval);
+    let retention = Duration::from_secs(args.artifact_retention_days * 24 * 60 * 60);
+    let limits = VolumeLimits {
+        max_size: args.max_volume_size * 1024 * 1024,
+        max_chapters: args.max_volume_chapters,
+    };
+    let policy = RetryPolicy {
+        max_attempts: args.max_attempts,
+        base_delay: Duration::from_secs(args.retry_delay),
+        max_delay: Duration::from_secs(args.max_retry_delay),
+    };
+    // exports left behind by a previous run are released right away
+    reap(&pool, lease, args.max_attempts).await;
+    purge(&pool, retention).await;
+    let mut last_reap = Instant::now();
+    schedule(&pool).await;
+    let mut last_schedule = Instant::now();
+
+    tracing::debug!("Worker polling for exports every {:?}", poll_interval);
+    loop {
+        if last_reap.elapsed() >= lease {
+            reap(&pool, lease, args.max_attempts).await;
+            purge(&pool, retention).await;
+            last_reap = Instant::now();
+        }
+        if last_schedule.elapsed() >= schedule_interval {
+            schedule(&pool).await;
+            last_schedule = Instant::now();
+        }
+
+        let pause = match claim_export(&pool).await {
+            Ok(Some(export)) => {
+                tracing::info!("Claimed export {} (attempt {})", export.id, export.attempts);
+                let heartbeat = lease::heartbeat(pool.clone(), export.id, lease);
+                export::run_export(&pool, export, &destinations, &fetcher, &policy, limits).await;
+                heartbeat.abort();
+                // there may be more work waiting, don't sleep
+                Duration::ZERO
+            }
+            Ok(None) => poll_interval,
+            Err(e) => {
+                tracing::error!("Failed to claim export: {:#}", e);
+                poll_interval
+            }
+        };
+
+        // checked first, a busy worker stops between two exports
+        tokio::select! {
+            biased;
+            _ = &mut shutdown => break,
+            _ = tokio::time::sleep(pause) => {},
+        }
+    }
+
+    pool.close().await;
+}
+
+async fn reap(pool: &PgPool, lease: Duration, max_attempts: i32) {
+    if let Err(e) = lease::reap(pool, lease, max_attempts).await {
+        tracing::error!("Failed to reap orphaned exports: {:#}", e);
+    }
+}
+
+async fn schedule(pool: &PgPool) {
+    if let Err(e) = scheduler::tick(pool).await {
+        tracing::error!("Failed to run subscriptions: {:#}", e);
+    }
+}
+
+async fn purge(pool: &PgPool, retention: Duration) {
+    if let Err(e) = artifact::purge(pool, retention).await {
+        tracing::error!("Failed to purge old artifacts: {:#}", e);
+    }
+}
+
+/// Marks the oldest pending export as being processed and returns it.
+///
+/// `SKIP LOCKED` lets several workers poll the same table without ever
+/// claiming the same row twice.
+async fn claim_export(pool: &PgPool) -> Result<Option<Export>, sqlx::Error> {
+    sqlx::query_as!(
+        Export,
+        "UPDATE exports
+        SET processing_started_at = CURRENT_TIMESTAMP,
+            heartbeat_at = CURRENT_TIMESTAMP,
+            attempts = attempts + 1
+        WHERE id = (
+            SELECT id FROM exports
+            WHERE processing_started_at IS NULL
+                AND (next_attempt_at IS NULL OR next_attempt_at <= CURRENT_TIMESTAMP)
+            ORDER BY COALESCE(next_attempt_at, created_at) ASC
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING *",
+    )
+    .fetch_optional(pool)
+    .await
+}
@@ -0,0 +1,180 @@
+use std::{fmt::Display, time::Duration};
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+
+/// Why an export could not be generated or delivered.
+#[derive(Debug)]
+pub enum Failure {
+    /// Worth trying again later, such as network errors or an overloaded
+    /// destination. `retry_after` is the delay the destination asked for.
+    Retryable {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    /// Trying again would fail the same way, such as a missing book or a
+    /// file rejected by the destination.
+    Permanent(String),
+}
+
+impl Failure {
+    pub fn retryable(message: impl Into<String>) -> Self {
+        Self::Retryable {
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    pub fn permanent(message: impl Into<String>) -> Self {
+        Self::Permanent(message.into())
+    }
+
+    /// Classifies an unsuccessful HTTP response: rate limits and server
+    /// errors are retried, anything else is the request's fault.
+    pub fn from_response(service: &str, res: &Response) -> Self {
+        let status = res.status();
+        let message = format!("{service} responded with {status}");
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            let retry_after = res
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<f64>().ok())
+                .map(Duration::from_secs_f64);
+
+            return Self::Retryable {
+                message,
+                retry_after,
+            };
+        }
+
+        Self::Permanent(message)
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Retryable { message, .. } => write!(f, "{message}"),
+            Self::Permanent(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+impl From<sqlx::Error> for Failure {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => Self::Permanent(e.to_string()),
+            _ => Self::retryable(e.to_string()),
+        }
+    }
+}
+
+/// How many times, and how often, failed exports and deliveries are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: i32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before the attempt following `attempts` failed ones, doubling
+    /// every time.
+    ///
+    /// `retry_after` wins when the destination asked for a specific delay.
+    pub fn delay(&self, attempts: i32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+
+        let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+        self.base_delay
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.max_delay)
+    }
+
+    /// Returns the delay before the next attempt, or `None` when the
+    /// failure should not be retried.
+    pub fn next_attempt(&self, attempts: i32, failure: &Failure) -> Option<Duration> {
+        match failure {
+            Failure::Retryable { retry_after, .. } if attempts < self.max_attempts => {
+                Some(self.delay(attempts, *retry_after))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        max_attempts: 4,
+        base_delay: Duration::from_secs(30),
+        max_delay: Duration::from_secs(600),
+    };
+
+    #[test]
+    fn delay_doubles_from_the_base() {
+        assert_eq!(POLICY.delay(1, None), Duration::from_secs(30));
+        assert_eq!(POLICY.delay(2, None), Duration::from_secs(60));
+        assert_eq!(POLICY.delay(3, None), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        assert_eq!(POLICY.delay(6, None), Duration::from_secs(600));
+        assert_eq!(POLICY.delay(i32::MAX, None), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn delay_before_any_attempt_is_the_base() {
+        assert_eq!(POLICY.delay(0, None), Duration::from_secs(30));
+        assert_eq!(POLICY.delay(-1, None), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn retry_after_wins_up_to_the_cap() {
+        let asked = Some(Duration::from_secs(5));
+        assert_eq!(POLICY.delay(3, asked), Duration::from_secs(5));
+
+        let asked = Some(Duration::from_secs(3600));
+        assert_eq!(POLICY.delay(1, asked), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn retryable_failures_are_retried_until_the_last_attempt() {
+        let failure = Failure::retryable("timed out");
+        assert_eq!(
+            POLICY.next_attempt(1, &failure),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            POLICY.next_attempt(3, &failure),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(POLICY.next_attempt(4, &failure), None);
+    }
+
+    #[test]
+    fn permanent_failures_are_not_retried() {
+        let failure = Failure::permanent("book not found");
+        assert_eq!(POLICY.next_attempt(1, &failure), None);
+    }
+
+    #[test]
+    fn database_errors_are_retryable_unless_the_row_is_missing() {
+        assert!(matches!(
+            Failure::from(sqlx::Error::PoolTimedOut),
+            Failure::Retryable { .. }
+        ));
+        assert!(matches!(
+            Failure::from(sqlx::Error::RowNotFound),
+            Failure::Permanent(_)
+        ));
+    }
+}
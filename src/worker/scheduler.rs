@@ -0,0 +1,136 @@
+use chrono::Utc;
+use models::export::{ExportKinds, ExportOptions};
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::server::subscriptions::{parse_schedule, Subscription};
+
+/// Queues a "since last export" export for every subscription that is due.
+///
+/// Subscriptions are locked while being looked at, so several workers can
+/// tick at the same time without queuing the same export twice.
+pub async fn tick(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let subscriptions = sqlx::query_as!(
+        Subscription,
+        "SELECT * FROM subscriptions ORDER BY id ASC FOR UPDATE SKIP LOCKED"
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for subscription in subscriptions {
+        run(&mut tx, subscription).await?;
+    }
+
+    tx.commit().await
+}
+
+async fn run(
+    tx: &mut Transaction<'_, Postgres>,
+    subscription: Subscription,
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    // scheduled runs happen once the quiet hours are over
+    if subscription.is_quiet(now.time()) {
+        return Ok(());
+    }
+
+    let scheduled = subscription.next_run_at.is_some_and(|at| at <= now);
+    if scheduled {
+        let next_run_at = subscription
+            .schedule
+            .as_deref()
+            .and_then(|schedule| parse_schedule(schedule).ok())
+            .and_then(|schedule| schedule.after(&now).next());
+        sqlx::query!(
+            "UPDATE subscriptions SET next_run_at = $2 WHERE id = $1",
+            subscription.id,
+            next_run_at,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    let new = sqlx::query!(
+        "SELECT COUNT(*) AS count, MAX(c.number_in_book) AS last
+        FROM chapters c
+        WHERE c.book_id = $2
+            AND NOT EXISTS (
+                SELECT 1 FROM watermarks w
+                WHERE w.user_id = $1
+                    AND w.book_id = c.book_id
+                    AND w.last_chapter >= c.number_in_book
+            )",
+        subscription.user_id,
+        subscription.book_id,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    let (Some(count), Some(last)) = (new.count, new.last) else {
+        return Ok(());
+    };
+
+    // without anything new since the last run (`None` is smaller than any
+    // chapter), a failed export would be queued over and over
+    let piled_up = subscription
+        .threshold
+        .is_some_and(|threshold| count >= i64::from(threshold))
+        && subscription.last_run_chapter < Some(last);
+    if !scheduled && !piled_up {
+        return Ok(());
+    }
+
+    // the previous export didn't move the watermark yet: it is waiting to be
+    // generated, being worked on, or has a delivery waiting to be retried
+    let pending = sqlx::query_scalar!(
+        "SELECT id FROM exports e
+        WHERE user_id = $1
+            AND (meta->>'SinceLastExport')::int = $2
+            AND sent = false
+            AND error IS NULL
+            AND (
+                processed_at IS NULL
+                OR processing_started_at IS NOT NULL
+                OR EXISTS (
+                    SELECT 1 FROM deliveries d
+                    WHERE d.export_id = e.id
+                        AND d.sent_at IS NULL
+                        AND d.next_attempt_at IS NOT NULL
+                )
+            )
+        LIMIT 1",
+        subscription.user_id,
+        subscription.book_id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    if pending.is_some() {
+        return Ok(());
+    }
+
+    let export = sqlx::query_scalar!(
+        "INSERT INTO exports (meta, options, user_id) VALUES ($1, $2, $3) RETURNING id",
+        serde_json::to_value(ExportKinds::SinceLastExport(subscription.book_id)).unwrap(),
+        serde_json::to_value(ExportOptions::default()).unwrap(),
+        subscription.user_id,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE subscriptions
+        SET last_run_at = CURRENT_TIMESTAMP,
+            last_run_chapter = $2
+        WHERE id = $1",
+        subscription.id,
+        last,
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    tracing::info!(
+        "Subscription {} queued export {} ({} new chapters)",
+        subscription.id,
+        export,
+        count
+    );
+    Ok(())
+}
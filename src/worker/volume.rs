@@ -0,0 +1,291 @@
+use models::epub::Series;
+use uuid::Uuid;
+
+use super::{epub::Epub, export::Page};
+
+/// Limits a single ePub has to fit in, bigger exports are split in volumes.
+#[derive(Debug, Clone, Copy)]
+pub struct VolumeLimits {
+    // in bytes
+    pub max_size: usize,
+    pub max_chapters: Option<usize>,
+}
+
+/// One of the ePubs an export is split in.
+pub struct Volume {
+    pub epub: Epub,
+    pub pages: Vec<Page>,
+    pub description: String,
+}
+
+/// Splits the pages of `epub` in volumes fitting in `limits`.
+///
+/// `numbers` are the chapter numbers of the pages, used to title volumes as
+/// "Vol. 2 (ch. 401–800)". The size of a page is its uncompressed HTML, so
+/// the compressed ePub ends up well below the limit. Images are only fetched
+/// while the ePub is written and aren't accounted for.
+pub fn split(
+    epub: Epub,
+    pages: Vec<Page>,
+    numbers: &[i32],
+    description: String,
+    limits: VolumeLimits,
+) -> Vec<Volume> {
+    // every volume carries the cover
+    let budget = limits.max_size.saturating_sub(cover_size(&epub));
+
+    let mut sizes = vec![];
+    let (mut count, mut size) = (0, 0);
+    for page in &pages {
+        let page = page_size(page);
+        let full = size + page > budget || limits.max_chapters.is_some_and(|max| count >= max);
+        // a chapter too big on its own still gets a volume
+        if count > 0 && full {
+            sizes.push(count);
+            (count, size) = (0, 0);
+        }
+        count += 1;
+        size += page;
+    }
+    sizes.push(count);
+
+    if sizes.len() == 1 {
+        return vec![Volume {
+            epub,
+            pages,
+            description,
+        }];
+    }
+
+    let total = sizes.len();
+    let mut pages = pages.into_iter();
+    let mut first = 0;
+    sizes
+        .into_iter()
+        .enumerate()
+        .map(|(idx, count)| {
+            let (from, to) = (numbers[first], numbers[first + count - 1]);
+            let chapters_range = match from == to {
+                true => format!("ch. {from}"),
+                false => format!("ch. {from}–{to}"),
+            };
+            first += count;
+
+            Volume {
+                epub: Epub {
+                    title: format!("{} Vol. {} ({chapters_range})", epub.title, idx + 1),
+                    author: epub.author.clone(),
+                    translator: epub.translator.clone(),
+                    cover: epub.cover.clone(),
+                    grayscale_cover: epub.grayscale_cover,
+                    style: epub.style,
+                    toc_position: epub.toc_position,
+                    numbered_toc: epub.numbered_toc,
+                    custom_css: epub.custom_css.clone(),
+                    identifier: part_identifier(&epub.identifier, from, to),
+                    language: epub.language.clone(),
+                    description: epub.description.clone(),
+                    subjects: epub.subjects.clone(),
+                    series: epub.series.as_ref().map(|series| Series {
+                        name: series.name.clone(),
+                        index: from,
+                    }),
+                    chapter_range: Some((from, to)),
+                    exported_on: epub.exported_on,
+                    templates: epub.templates.clone(),
+                },
+                pages: pages.by_ref().take(count).collect(),
+                description: format!("{description}, volume {} of {total}", idx + 1),
+            }
+        })
+        .collect()
+}
+
+/// Size of the cover, decoded from base64.
+pub fn cover_size(epub: &Epub) -> usize {
+    epub.cover.as_ref().map_or(0, |cover| cover.len() / 4 * 3)
+}
+
+/// Uncompressed size of a page, see [`split`].
+pub fn page_size(page: &Page) -> usize {
+    page.title.len() + page.size
+}
+
+/// Identifier of an export holding chapters `from` to `to` of the ePub
+/// identified by `identifier`.
+///
+/// Exporting the same chapters again gives the same identifier, so readers
+/// replace the previous file instead of keeping both.
+pub fn part_identifier(identifier: &Uuid, from: i32, to: i32) -> Uuid {
+    Uuid::new_v5(identifier, format!("{from}-{to}").as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn epub() -> Epub {
+        Epub {
+            title: "Book".to_owned(),
+            author: None,
+            translator: None,
+            cover: None,
+            grayscale_cover: false,
+            style: Default::default(),
+            toc_position: Default::default(),
+            numbered_toc: false,
+            custom_css: None,
+            identifier: Uuid::nil(),
+            language: "en".to_owned(),
+            description: None,
+            subjects: vec![],
+            series: Some(Series {
+                name: "Book".to_owned(),
+                index: 1,
+            }),
+            chapter_range: Some((1, 5)),
+            exported_on: NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+            templates: Default::default(),
+        }
+    }
+
+    // titled "c1", "c2"... so a page's size is `size` plus 2
+    fn pages(sizes: &[usize]) -> (Vec<Page>, Vec<i32>) {
+        let pages: Vec<Page> = sizes
+            .iter()
+            .enumerate()
+            .map(|(idx, size)| Page {
+                number: idx as i32 + 1,
+                title: format!("c{}", idx + 1),
+                size: *size,
+                chapter_id: Some(idx as i32 + 1),
+            })
+            .collect();
+        let numbers = pages.iter().map(|page| page.number).collect();
+
+        (pages, numbers)
+    }
+
+    fn split_sizes(sizes: &[usize], limits: VolumeLimits) -> Vec<Volume> {
+        let (pages, numbers) = pages(sizes);
+        split(epub(), pages, &numbers, "Export".to_owned(), limits)
+    }
+
+    fn chapters(volume: &Volume) -> Vec<i32> {
+        volume.pages.iter().map(|page| page.number).collect()
+    }
+
+    #[test]
+    fn fitting_export_is_left_alone() {
+        let volumes = split_sizes(
+            &[8, 8, 8],
+            VolumeLimits {
+                max_size: 30,
+                max_chapters: None,
+            },
+        );
+
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].epub, epub());
+        assert_eq!(volumes[0].description, "Export");
+        assert_eq!(chapters(&volumes[0]), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn volume_exactly_at_the_limit() {
+        let volumes = split_sizes(
+            &[8, 8, 8, 8],
+            VolumeLimits {
+                max_size: 20,
+                max_chapters: None,
+            },
+        );
+
+        let chapters: Vec<Vec<i32>> = volumes.iter().map(chapters).collect();
+        assert_eq!(chapters, vec![vec![1, 2], vec![3, 4]]);
+    }
+
+    #[test]
+    fn volumes_are_titled_and_identified_by_their_chapters() {
+        let volumes = split_sizes(
+            &[8, 8, 8],
+            VolumeLimits {
+                max_size: 20,
+                max_chapters: None,
+            },
+        );
+
+        assert_eq!(volumes.len(), 2);
+        assert_eq!(volumes[0].epub.title, "Book Vol. 1 (ch. 1–2)");
+        assert_eq!(volumes[1].epub.title, "Book Vol. 2 (ch. 3)");
+        assert_eq!(volumes[1].description, "Export, volume 2 of 2");
+        assert_eq!(volumes[1].epub.chapter_range, Some((3, 3)));
+        assert_eq!(volumes[1].epub.series.as_ref().unwrap().index, 3);
+        assert_eq!(
+            volumes[1].epub.identifier,
+            part_identifier(&Uuid::nil(), 3, 3)
+        );
+    }
+
+    #[test]
+    fn oversize_chapter_gets_its_own_volume() {
+        let volumes = split_sizes(
+            &[8, 100, 8],
+            VolumeLimits {
+                max_size: 20,
+                max_chapters: None,
+            },
+        );
+
+        let chapters: Vec<Vec<i32>> = volumes.iter().map(chapters).collect();
+        assert_eq!(chapters, vec![vec![1], vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn single_oversize_chapter() {
+        let volumes = split_sizes(
+            &[100],
+            VolumeLimits {
+                max_size: 20,
+                max_chapters: None,
+            },
+        );
+
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].epub.title, "Book");
+        assert_eq!(chapters(&volumes[0]), vec![1]);
+    }
+
+    #[test]
+    fn chapter_limit() {
+        let volumes = split_sizes(
+            &[1, 1, 1, 1, 1],
+            VolumeLimits {
+                max_size: usize::MAX,
+                max_chapters: Some(2),
+            },
+        );
+
+        let chapters: Vec<Vec<i32>> = volumes.iter().map(chapters).collect();
+        assert_eq!(chapters, vec![vec![1, 2], vec![3, 4], vec![5]]);
+    }
+
+    #[test]
+    fn cover_counts_against_every_volume() {
+        let (pages, numbers) = pages(&[8, 8]);
+        let epub = Epub {
+            // decodes to 6 bytes
+            cover: Some("AAAAAAAA".to_owned()),
+            ..epub()
+        };
+        let limits = VolumeLimits {
+            max_size: 25,
+            max_chapters: None,
+        };
+
+        let volumes = split(epub, pages, &numbers, "Export".to_owned(), limits);
+        assert_eq!(volumes.len(), 2);
+    }
+}